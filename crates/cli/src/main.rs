@@ -1,12 +1,26 @@
-use anyhow::Result;
+use anyhow::{Result, bail};
+use buddy_up_lib::Decay;
+use buddy_up_lib::Exclusions;
+use buddy_up_lib::GeneticParams;
 use buddy_up_lib::History;
+use buddy_up_lib::OddPolicy;
+use buddy_up_lib::PairingConfig;
 use buddy_up_lib::People;
-use clap::{Parser, Subcommand};
+use buddy_up_lib::Pins;
+use buddy_up_lib::Preferences;
+use buddy_up_lib::Progress;
+use buddy_up_lib::Solver;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::fs::File;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
 use tracing::{debug, info};
 
+/// How often long searches log their progress.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 #[command(propagate_version = true)]
@@ -20,6 +34,8 @@ struct Cli {
     verbose: u8,
 }
 
+// parsed once at startup, so the size of the biggest command doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     Pair {
@@ -31,9 +47,345 @@ enum Commands {
         /// group of people. Will be created if it doesn't exist.
         #[arg(short, long)]
         output_dir: PathBuf,
+
+        /// The path to a CSV file of pairs that must meet this round, as rows of `id, id`. They're
+        /// saved to the history like any other pair.
+        #[arg(short, long)]
+        pins: Option<PathBuf>,
+
+        /// IDs of people who are away this round, like `--absent 3,7`. They stay in the input, but
+        /// aren't paired.
+        #[arg(short, long, value_delimiter = ',')]
+        absent: Vec<usize>,
+
+        /// The path to a file of IDs of people who are away this round, separated by commas or
+        /// new lines.
+        #[arg(long)]
+        absent_file: Option<PathBuf>,
+
+        /// Show this many different groupings to choose from, best first, instead of saving one.
+        #[arg(
+            long,
+            value_name = "COUNT",
+            default_value_t = 1,
+            conflicts_with = "pick"
+        )]
+        alternatives: usize,
+
+        /// Save option N from `--alternatives` instead of the best one. Use the same `--seed` to
+        /// get the same options again.
+        #[arg(
+            long,
+            value_name = "N",
+            default_value_t = 1,
+            value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+        )]
+        pick: usize,
+
+        #[command(flatten)]
+        pairing: PairingArgs,
+    },
+    /// Re-does the newest round in the history, keeping some of its groups and pairing everyone
+    /// else again. The new round replaces the old one.
+    Reroll {
+        /// The path to a CSV file that defines the people input. Should be rows formatted like `id, name`.
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// The directory holding the pairing history, with the round to re-do as its newest file.
+        #[arg(short, long)]
+        output_dir: PathBuf,
+
+        /// Two IDs of a group to keep, like `--keep 1,2`. Can be repeated. Everyone in a group
+        /// that isn't kept is paired again, with someone new if possible.
+        #[arg(short, long, value_name = "ID,ID", value_parser = parse_pair)]
+        keep: Vec<(usize, usize)>,
+
+        #[command(flatten)]
+        pairing: PairingArgs,
+    },
+    /// Plans several rounds of pairs in advance, without repeating a pair until everyone has met
+    /// everyone else. Nothing is saved to the history.
+    Plan {
+        /// The path to a CSV file that defines the people input. Should be rows formatted like `id, name`.
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// The directory holding the pairing history to plan around.
+        #[arg(short, long)]
+        output_dir: PathBuf,
+
+        /// How many rounds to plan.
+        #[arg(short, long)]
+        rounds: usize,
+
+        /// Optimize all rounds together against the history instead of using a fixed round robin.
+        /// Slower, but better at avoiding repeats when the history already has some.
+        #[arg(short, long)]
+        lookahead: bool,
+
+        /// Only used with `--lookahead`.
+        #[command(flatten)]
+        pairing: PairingArgs,
     },
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum SolverArg {
+    Genetic,
+    Exact,
+    Heuristic,
+}
+
+/// The rules for pairing people, shared by everything that pairs them.
+#[derive(Args, Debug)]
+struct PairingArgs {
+    /// How many people to put in each group. Use 2 for pairs, 3 for triads and so on. If the
+    /// people don't split evenly, some groups will be one smaller.
+    #[arg(short, long, default_value_t = 2)]
+    group_size: usize,
+
+    /// The algorithm used to find the pairs. `exact` always finds the pairing with the fewest
+    /// repeats, but only works for pairs. `heuristic` is the quickest for thousands of people.
+    #[arg(short, long, value_enum, default_value_t = SolverArg::Genetic)]
+    solver: SolverArg,
+
+    /// Seed for the random number generator. The same seed, people and history always give the
+    /// same pairs. The seed used is saved in the history file, so any run can be repeated.
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// The path to a CSV file of people who must never be paired, as rows of `id, id`.
+    #[arg(short = 'x', long)]
+    exclusions: Option<PathBuf>,
+
+    /// The path to a CSV file of who would like to meet whom, as rows of `id, id, rating`. The
+    /// rating goes from -1, rather not, to 1, would love to.
+    #[arg(long)]
+    preferences: Option<PathBuf>,
+
+    /// How much the preferences count. At the default of 0.25, even the strongest preferences
+    /// count less than a repeat.
+    #[arg(
+        long,
+        value_name = "WEIGHT",
+        default_value_t = 0.25,
+        requires = "preferences"
+    )]
+    preference_weight: f64,
+
+    /// Let past meetings count half as much for every this many days before the newest round, so
+    /// pairs that met long ago become eligible again sooner.
+    #[arg(long, value_name = "DAYS", conflicts_with = "window")]
+    half_life: Option<u64>,
+
+    /// Only count meetings from the last this many rounds of history.
+    #[arg(long, value_name = "ROUNDS")]
+    window: Option<usize>,
+
+    /// Never pair people who met in the last this many rounds of history. Fails if there's no
+    /// way around it.
+    #[arg(long, value_name = "ROUNDS")]
+    cooldown: Option<usize>,
+
+    /// Prefer pairing people with different values for this attribute from the input, like
+    /// `team`. Add `=WEIGHT` to say how much it matters: 1.0, the default, makes a same-team
+    /// pair as bad as a repeat. Can be repeated.
+    #[arg(short, long, value_name = "ATTRIBUTE[=WEIGHT]", value_parser = parse_diversity)]
+    diversity: Vec<(String, f64)>,
+
+    /// Only pair mentors with mentees, going by the `role=mentor` or `role=mentee` attribute
+    /// everyone needs in the input. If one side is bigger, the rest sit the round out.
+    #[arg(short, long)]
+    mentoring: bool,
+
+    /// What to do with the odd one out when pairing an odd number of people: take turns
+    /// sitting out, or join a pair to make one group of three.
+    #[arg(long, value_enum, default_value_t = OddArg::SitOut)]
+    odd: OddArg,
+
+    /// Only pair people whose working hours overlap at least this many hours, going by their
+    /// `timezone=+5:30` and `hours=9-17` attributes. People without a time zone can meet anyone.
+    #[arg(long, value_name = "HOURS", value_parser = parse_overlap)]
+    min_overlap: Option<Duration>,
+
+    #[command(flatten)]
+    genetic: GeneticArgs,
+}
+
+impl PairingArgs {
+    fn config(&self) -> Result<PairingConfig> {
+        let mut config = PairingConfig::new()
+            .with_group_size(self.group_size)
+            .with_solver(self.solver.into())
+            .with_mentoring(self.mentoring)
+            .with_odd_policy(self.odd.into());
+        config = self.genetic.apply(config);
+        if let Some(min_overlap) = self.min_overlap {
+            config = config.with_min_overlap(min_overlap);
+        }
+        if let Some(seed) = self.seed {
+            config = config.with_seed(seed);
+        }
+        if let Some(exclusions) = &self.exclusions {
+            let f = File::open(exclusions)?;
+            config = config.with_exclusions(Exclusions::from_csv(f)?);
+        }
+        if let Some(preferences) = &self.preferences {
+            let f = File::open(preferences)?;
+            config = config.with_preferences(Preferences::from_csv(f)?, self.preference_weight);
+        }
+        if let Some(days) = self.half_life {
+            let half_life = Duration::from_secs(days * 24 * 60 * 60);
+            config = config.with_decay(Decay::HalfLife(half_life));
+        } else if let Some(rounds) = self.window {
+            config = config.with_decay(Decay::Window(rounds));
+        }
+        if let Some(rounds) = self.cooldown {
+            config = config.with_cooldown(rounds);
+        }
+        for (attribute, weight) in &self.diversity {
+            config = config.with_diversity(attribute, *weight);
+        }
+        Ok(config)
+    }
+}
+
+/// Tuning for the genetic solver. Anything left out keeps the library's default.
+#[derive(Args, Debug, Default, PartialEq)]
+#[command(next_help_heading = "Genetic solver")]
+struct GeneticArgs {
+    /// How many candidate pairings to keep around. Bigger searches more widely, but is slower.
+    #[arg(long)]
+    population_size: Option<usize>,
+
+    /// Stop after this many generations without finding anything better. Raise it for big
+    /// groups, lower it to finish small groups instantly.
+    #[arg(long)]
+    max_stale_generations: Option<usize>,
+
+    /// How likely each candidate is to get two people swapped every generation, from 0 to 1.
+    #[arg(long)]
+    mutation_rate: Option<f32>,
+
+    /// How much of the population makes offspring every generation, from 0 to 1.
+    #[arg(long)]
+    crossover_rate: Option<f32>,
+
+    /// The share of offspring that replace their parents every generation, from 0 to 1.
+    #[arg(long, requires = "elitism_rate")]
+    replacement_rate: Option<f32>,
+
+    /// The share of the best parents that are always kept, from 0 to 1.
+    #[arg(long, requires = "replacement_rate")]
+    elitism_rate: Option<f32>,
+
+    /// Stop searching after this many seconds and go with the best pairs found so far.
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    time_limit: Option<Duration>,
+}
+
+impl GeneticArgs {
+    fn params(&self) -> GeneticParams {
+        GeneticParams {
+            population_size: self.population_size,
+            max_stale_generations: self.max_stale_generations,
+            mutation_rate: self.mutation_rate,
+            crossover_rate: self.crossover_rate,
+            elite_selection: self.replacement_rate.zip(self.elitism_rate),
+            time_limit: self.time_limit,
+        }
+    }
+
+    fn apply(&self, config: PairingConfig) -> PairingConfig {
+        config.with_genetic_params(self.params())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OddArg {
+    SitOut,
+    Triad,
+}
+
+impl From<OddArg> for OddPolicy {
+    fn from(arg: OddArg) -> Self {
+        match arg {
+            OddArg::SitOut => OddPolicy::SitOut,
+            OddArg::Triad => OddPolicy::Triad,
+        }
+    }
+}
+
+impl From<SolverArg> for Solver {
+    fn from(arg: SolverArg) -> Self {
+        match arg {
+            SolverArg::Genetic => Solver::Genetic,
+            SolverArg::Exact => Solver::Exact,
+            SolverArg::Heuristic => Solver::Heuristic,
+        }
+    }
+}
+
+fn parse_diversity(arg: &str) -> Result<(String, f64), String> {
+    match arg.split_once('=') {
+        Some((key, weight)) => {
+            let weight = weight
+                .parse()
+                .map_err(|_| format!("'{weight}' is not a number"))?;
+            Ok((key.to_string(), weight))
+        }
+        None => Ok((arg.to_string(), 1.0)),
+    }
+}
+
+/// Parses `--keep 1,2` into a pair of IDs.
+fn parse_pair(arg: &str) -> Result<(usize, usize), String> {
+    let (a, b) = arg
+        .split_once(',')
+        .ok_or_else(|| format!("'{arg}' should be two IDs, like '1,2'"))?;
+    let id = |id: &str| {
+        id.trim()
+            .parse()
+            .map_err(|_| format!("'{id}' is not an ID"))
+    };
+    Ok((id(a)?, id(b)?))
+}
+
+/// Parses `--min-overlap 2.5` into a duration of hours.
+fn parse_overlap(arg: &str) -> Result<Duration, String> {
+    let hours: f64 = arg
+        .parse()
+        .map_err(|_| format!("'{arg}' is not a number"))?;
+    Duration::try_from_secs_f64(hours * 60.0 * 60.0)
+        .map_err(|_| format!("'{arg}' is not a number of hours"))
+}
+
+/// Parses `--time-limit 2.5` into a duration of seconds.
+fn parse_seconds(arg: &str) -> Result<Duration, String> {
+    let seconds: f64 = arg
+        .parse()
+        .map_err(|_| format!("'{arg}' is not a number"))?;
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("'{arg}' is not a number of seconds"))
+}
+
+/// Logs how the search is going about once a second, so long runs don't look stuck.
+fn report_progress() -> impl Fn(Progress) + Send + Sync + 'static {
+    let last = Mutex::new(Duration::ZERO);
+    move |progress| {
+        let mut last = last.lock().unwrap();
+        if progress.elapsed >= *last + PROGRESS_INTERVAL {
+            *last = progress.elapsed;
+            if let Some(score) = progress.best_score {
+                info!(
+                    "Generation {}, best score so far {score}...",
+                    progress.generation
+                );
+            }
+        }
+    }
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
@@ -48,22 +400,60 @@ fn main() -> Result<()> {
     initialize_logging(level);
 
     match &cli.command {
-        Commands::Pair { input, output_dir } => {
-            pair(input, output_dir)?;
+        Commands::Pair {
+            input,
+            output_dir,
+            pins,
+            absent,
+            absent_file,
+            alternatives,
+            pick,
+            pairing,
+        } => {
+            let mut config = pairing.config()?.with_absent(absent.iter().copied());
+            if let Some(absent_file) = absent_file {
+                config = config.with_absent(read_ids(absent_file)?);
+            }
+            if let Some(pins) = pins {
+                let f = File::open(pins)?;
+                config = config.with_pins(Pins::from_csv(f)?);
+            }
+            if *alternatives > 1 {
+                show_alternatives(input, output_dir, &config, *alternatives)?;
+            } else {
+                pair(input, output_dir, &config, *pick)?;
+            }
+        }
+        Commands::Reroll {
+            input,
+            output_dir,
+            keep,
+            pairing,
+        } => {
+            reroll(input, output_dir, &pairing.config()?, keep)?;
+        }
+        Commands::Plan {
+            input,
+            output_dir,
+            rounds,
+            lookahead,
+            pairing,
+        } => {
+            let config = lookahead.then(|| pairing.config()).transpose()?;
+            plan(input, output_dir, config.as_ref(), *rounds)?;
         }
     }
 
     Ok(())
 }
 
-fn pair(input: &Path, history_dir: &Path) -> Result<()> {
-    let output_dir = history_dir.to_string_lossy();
-
+/// Reads the people and their history, and logs what we found.
+fn read_input(input: &Path, history_dir: &Path) -> Result<(People, History)> {
     let f = File::open(input)?;
     let people = People::from_csv(f)?;
 
     // generate history from history directory (which contains the pairing files)
-    let history = History::from_dir(&output_dir)?;
+    let history = History::from_dir(&history_dir.to_string_lossy())?;
 
     let tr_num_pairs = history.stats().pairs;
     let tr_max_num_pairs = (people.len().pow(2) - people.len()) / 2;
@@ -73,14 +463,138 @@ fn pair(input: &Path, history_dir: &Path) -> Result<()> {
     );
     debug!("History min iterations: {}", history.min());
     debug!("History max iterations: {}", history.max());
+    debug!("Byes so far: {:?}", history.stats().byes);
+    Ok((people, history))
+}
+
+/// Pairs everyone and saves the groups to the history. `pick` says which of the alternatives to
+/// go with, 1 being the best.
+fn pair(input: &Path, history_dir: &Path, config: &PairingConfig, pick: usize) -> Result<()> {
+    let output_dir = history_dir.to_string_lossy();
+    let (people, history) = read_input(input, history_dir)?;
 
-    let pairs = buddy_up_lib::pair(people, &history);
+    let config = config.clone().with_progress(report_progress());
+    let mut outcomes = buddy_up_lib::pair_alternatives(people, &history, &config, pick)?;
+    if outcomes.len() < pick {
+        bail!(
+            "There are only {} different groupings to pick from.",
+            outcomes.len()
+        );
+    }
+    let outcome = outcomes.swap_remove(pick - 1);
+    info!("{outcome}.");
+    for ((a, b), met) in outcome.previous_meetings() {
+        if *met > 0 {
+            debug!("{a} and {b} met {met} times before.");
+        }
+    }
+    let groups = outcome.into_groups();
+    if let Some(seed) = groups.seed() {
+        info!("Used seed {seed}.");
+    }
 
     // serialize to json and save
-    buddy_up_lib::save_history(&pairs, &output_dir)?;
+    buddy_up_lib::save_history(&groups, &output_dir)?;
+
+    // now print the groups
+    println!("{}", buddy_up_lib::print_table(groups));
+    Ok(())
+}
+
+/// Prints up to `count` alternative groupings to choose from, without saving any of them.
+fn show_alternatives(
+    input: &Path,
+    history_dir: &Path,
+    config: &PairingConfig,
+    count: usize,
+) -> Result<()> {
+    let (people, history) = read_input(input, history_dir)?;
+
+    let config = config.clone().with_progress(report_progress());
+    let outcomes = buddy_up_lib::pair_alternatives(people, &history, &config, count)?;
+    let seed = outcomes.first().and_then(|outcome| outcome.groups().seed());
+    for (n, outcome) in outcomes.into_iter().enumerate() {
+        println!("Option {}: {outcome}", n + 1);
+        println!("{}", buddy_up_lib::print_table(outcome.into_groups()));
+    }
+
+    // the same seed gets the same options again
+    let seed = seed
+        .map(|seed| format!(" --seed {seed}"))
+        .unwrap_or_default();
+    info!("Nothing was saved. Run again with{seed} --pick N to save option N.");
+    Ok(())
+}
 
-    // now print the pairs
-    println!("{}", buddy_up_lib::print_table(pairs));
+/// Re-does the newest round in the history, keeping the groups with the `keep` pairs in them, and
+/// saves it in place of the old one.
+fn reroll(
+    input: &Path,
+    history_dir: &Path,
+    config: &PairingConfig,
+    keep: &[(usize, usize)],
+) -> Result<()> {
+    let output_dir = history_dir.to_string_lossy();
+    let f = File::open(input)?;
+    let people = People::from_csv(f)?;
+
+    let Some((path, previous)) = buddy_up_lib::last_round(&output_dir)? else {
+        bail!("There's no round in {output_dir} to re-roll.");
+    };
+    // the round being redone mustn't count as history
+    let history = History::before_last(&output_dir)?;
+    info!("Re-rolling {path:?}, keeping {} pairs.", keep.len());
+
+    let config = config.clone().with_progress(report_progress());
+    let outcome = buddy_up_lib::reroll(people, &history, &config, &previous, keep)?;
+    info!("{outcome}.");
+    let groups = outcome.into_groups();
+
+    // save first, so a failed save doesn't lose the old round
+    let saved = buddy_up_lib::save_history(&groups, &output_dir)?;
+    // within the same second, the new round already took the old one's place
+    if saved.file_name() != path.file_name() {
+        std::fs::remove_file(&path)?;
+    }
+
+    println!("{}", buddy_up_lib::print_table(groups));
+    Ok(())
+}
+
+/// Reads IDs separated by commas, whitespace or new lines.
+fn read_ids(path: &Path) -> Result<Vec<usize>> {
+    let ids = std::fs::read_to_string(path)?
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|id| !id.is_empty())
+        .map(str::parse)
+        .collect::<Result<_, _>>()?;
+    Ok(ids)
+}
+
+/// Plans `rounds` rounds with a fixed round robin, or against the history with `config` if there
+/// is one.
+fn plan(
+    input: &Path,
+    history_dir: &Path,
+    config: Option<&PairingConfig>,
+    rounds: usize,
+) -> Result<()> {
+    let output_dir = history_dir.to_string_lossy();
+
+    let f = File::open(input)?;
+    let people = People::from_csv(f)?;
+    let history = History::from_dir(&output_dir)?;
+
+    let schedule = if let Some(config) = config {
+        let config = config.clone().with_progress(report_progress());
+        buddy_up_lib::plan_ahead(people, &history, &config, rounds)?
+    } else {
+        buddy_up_lib::plan_schedule(people, &history, rounds)?
+    };
+    for (n, pairs) in schedule.into_iter().enumerate() {
+        println!("Round {}", n + 1);
+        println!("{}", buddy_up_lib::print_table(pairs));
+    }
     Ok(())
 }
 
@@ -105,10 +619,39 @@ mod tests {
             Cli::try_parse_from(["app", "pair", "-i", "people.csv", "-o", "output_dir"]).unwrap();
 
         match cli.command {
-            Commands::Pair { input, output_dir } => {
+            Commands::Pair {
+                input,
+                output_dir,
+                pins,
+                absent,
+                absent_file,
+                alternatives,
+                pick,
+                pairing,
+            } => {
                 assert_eq!(input, PathBuf::from("people.csv"));
                 assert_eq!(output_dir, PathBuf::from("output_dir"));
+                assert_eq!(pairing.group_size, 2);
+                assert_eq!(pairing.solver, SolverArg::Genetic);
+                assert_eq!(pairing.seed, None);
+                assert_eq!(pairing.exclusions, None);
+                assert_eq!(pins, None);
+                assert_eq!(pairing.preferences, None);
+                assert_eq!(pairing.preference_weight, 0.25);
+                assert_eq!(pairing.half_life, None);
+                assert_eq!(pairing.window, None);
+                assert_eq!(pairing.cooldown, None);
+                assert!(pairing.diversity.is_empty());
+                assert!(!pairing.mentoring);
+                assert_eq!(pairing.odd, OddArg::SitOut);
+                assert_eq!(pairing.genetic, GeneticArgs::default());
+                assert!(absent.is_empty());
+                assert_eq!(absent_file, None);
+                assert_eq!(pairing.min_overlap, None);
+                assert_eq!(alternatives, 1);
+                assert_eq!(pick, 1);
             }
+            _ => panic!("expected the pair command"),
         }
 
         assert_eq!(cli.verbose, 0);
@@ -138,7 +681,12 @@ mod tests {
         input_file.write_str("1,Alice\n2,Bob\n3,Charlie\n4,David\n")?;
 
         // Run the pair function
-        pair(input_file.path(), history_dir_path)?;
+        pair(
+            input_file.path(),
+            history_dir_path,
+            &PairingConfig::default(),
+            1,
+        )?;
 
         // Verify a history file was created in the output directory
         let files = std::fs::read_dir(history_dir_path)?
@@ -150,6 +698,525 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_cli_group_size() {
+        let cli = Cli::try_parse_from([
+            "app",
+            "pair",
+            "-i",
+            "people.csv",
+            "-o",
+            "output_dir",
+            "--group-size",
+            "4",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Pair { pairing, .. } => assert_eq!(pairing.group_size, 4),
+            _ => panic!("expected the pair command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_solver() {
+        let cli = Cli::try_parse_from([
+            "app",
+            "pair",
+            "-i",
+            "people.csv",
+            "-o",
+            "output_dir",
+            "--solver",
+            "exact",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Pair { pairing, .. } => assert_eq!(pairing.solver, SolverArg::Exact),
+            _ => panic!("expected the pair command"),
+        }
+
+        let args = ["app", "pair", "-i", "p.csv", "-o", "out", "-s", "heuristic"];
+        match Cli::try_parse_from(args).unwrap().command {
+            Commands::Pair { pairing, .. } => {
+                assert_eq!(Solver::from(pairing.solver), Solver::Heuristic);
+            }
+            _ => panic!("expected the pair command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_seed() {
+        let cli = Cli::try_parse_from([
+            "app",
+            "pair",
+            "-i",
+            "people.csv",
+            "-o",
+            "output_dir",
+            "--seed",
+            "42",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Pair { pairing, .. } => assert_eq!(pairing.seed, Some(42)),
+            _ => panic!("expected the pair command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_decay() {
+        let args = ["app", "pair", "-i", "people.csv", "-o", "output_dir"];
+        let cli = Cli::try_parse_from(args.iter().chain(&["--half-life", "90"])).unwrap();
+        match cli.command {
+            Commands::Pair { pairing, .. } => assert_eq!(pairing.half_life, Some(90)),
+            _ => panic!("expected the pair command"),
+        }
+
+        let cli = Cli::try_parse_from(args.iter().chain(&["--window", "4"])).unwrap();
+        match cli.command {
+            Commands::Pair { pairing, .. } => assert_eq!(pairing.window, Some(4)),
+            _ => panic!("expected the pair command"),
+        }
+
+        // only one kind of decay at a time
+        let both = ["--half-life", "90", "--window", "4"];
+        assert!(Cli::try_parse_from(args.iter().chain(&both)).is_err());
+    }
+
+    #[test]
+    fn test_cli_diversity() {
+        let cli = Cli::try_parse_from([
+            "app",
+            "pair",
+            "-i",
+            "people.csv",
+            "-o",
+            "output_dir",
+            "--diversity",
+            "team",
+            "-d",
+            "office=0.5",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Pair { pairing, .. } => assert_eq!(
+                pairing.diversity,
+                vec![("team".to_string(), 1.0), ("office".to_string(), 0.5)]
+            ),
+            _ => panic!("expected the pair command"),
+        }
+
+        let args = ["app", "pair", "-i", "people.csv", "-o", "output_dir"];
+        assert!(Cli::try_parse_from(args.iter().chain(&["-d", "team=lots"])).is_err());
+    }
+
+    #[test]
+    fn test_pair_mentoring() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = assert_fs::NamedTempFile::new("people.csv")?;
+        input_file.write_str("1,Alice,role=mentor\n2,Bob,role=mentee\n3,Charlie,role=mentee\n")?;
+
+        let cli = Cli::try_parse_from(["app", "pair", "-i", "p.csv", "-o", "out", "--mentoring"])?;
+        match cli.command {
+            Commands::Pair { pairing, .. } => assert!(pairing.mentoring),
+            _ => panic!("expected the pair command"),
+        }
+
+        let config = PairingConfig::new().with_mentoring(true);
+        pair(input_file.path(), temp_dir.path(), &config, 1)?;
+
+        // the mentees never meet each other
+        let history = History::from_dir(&temp_dir.path().to_string_lossy())?;
+        assert_eq!(history.get((2, 3)), None);
+        assert_eq!(history.stats().pairs, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pair_odd_triad() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = assert_fs::NamedTempFile::new("people.csv")?;
+        input_file.write_str("1,Alice\n2,Bob\n3,Charlie\n")?;
+
+        let cli =
+            Cli::try_parse_from(["app", "pair", "-i", "p.csv", "-o", "out", "--odd", "triad"])?;
+        match cli.command {
+            Commands::Pair { pairing, .. } => assert_eq!(pairing.odd, OddArg::Triad),
+            _ => panic!("expected the pair command"),
+        }
+
+        let config = PairingConfig::new().with_odd_policy(OddPolicy::Triad);
+        pair(input_file.path(), temp_dir.path(), &config, 1)?;
+
+        // everyone met everyone in the triad, and nobody sat out
+        let history = History::from_dir(&temp_dir.path().to_string_lossy())?;
+        assert_eq!(history.stats().pairs, 3);
+        assert!(history.stats().byes.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_genetic() {
+        let args = ["app", "pair", "-i", "people.csv", "-o", "output_dir"];
+        let tuning = [
+            "--population-size",
+            "200",
+            "--max-stale-generations",
+            "50",
+            "--mutation-rate",
+            "0.3",
+            "--crossover-rate",
+            "0.8",
+            "--replacement-rate",
+            "0.4",
+            "--elitism-rate",
+            "0.1",
+            "--time-limit",
+            "2.5",
+        ];
+        let cli = Cli::try_parse_from(args.iter().chain(&tuning)).unwrap();
+        match cli.command {
+            Commands::Pair { pairing, .. } => {
+                let config = pairing.genetic.apply(PairingConfig::new());
+                assert_eq!(config.population_size(), 200);
+                assert_eq!(config.max_stale_generations(), 50);
+                assert!((config.mutation_rate() - 0.3).abs() < f32::EPSILON);
+                assert!((config.crossover_rate() - 0.8).abs() < f32::EPSILON);
+                assert_eq!(config.elite_selection(), (0.4, 0.1));
+                assert_eq!(config.time_limit(), Some(Duration::from_millis(2500)));
+            }
+            _ => panic!("expected the pair command"),
+        }
+
+        // the selection rates go together
+        assert!(Cli::try_parse_from(args.iter().chain(&["--elitism-rate", "0.1"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_seconds() {
+        assert_eq!(parse_seconds("0.5"), Ok(Duration::from_millis(500)));
+        assert!(parse_seconds("-1").is_err());
+        assert!(parse_seconds("soon").is_err());
+    }
+
+    #[test]
+    fn test_pair_min_overlap() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = assert_fs::NamedTempFile::new("people.csv")?;
+        input_file.write_str(
+            "1,Alice,timezone=+1\n2,Bob,timezone=-8\n3,Charlie,timezone=+2\n4,David,timezone=-7\n",
+        )?;
+
+        let args = ["app", "pair", "-i", "p.csv", "-o", "out"];
+        let cli = Cli::try_parse_from(args.iter().chain(&["--min-overlap", "1.5"]))?;
+        match cli.command {
+            Commands::Pair { pairing, .. } => {
+                assert_eq!(pairing.min_overlap, Some(Duration::from_secs(90 * 60)));
+            }
+            _ => panic!("expected the pair command"),
+        }
+        assert!(Cli::try_parse_from(args.iter().chain(&["--min-overlap", "-1"])).is_err());
+
+        let config = PairingConfig::new().with_min_overlap(Duration::from_secs(60 * 60));
+        pair(input_file.path(), temp_dir.path(), &config, 1)?;
+
+        let history = History::from_dir(&temp_dir.path().to_string_lossy())?;
+        assert_eq!(history.get((1, 3)), Some(1));
+        assert_eq!(history.get((2, 4)), Some(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_absent() -> Result<()> {
+        let args = ["app", "pair", "-i", "people.csv", "-o", "output_dir"];
+        let cli = Cli::try_parse_from(args.iter().chain(&["--absent", "3,7", "-a", "9"]))?;
+        match cli.command {
+            Commands::Pair { absent, .. } => assert_eq!(absent, vec![3, 7, 9]),
+            _ => panic!("expected the pair command"),
+        }
+
+        let absent_file = assert_fs::NamedTempFile::new("absent.csv")?;
+        absent_file.write_str("3, 7\n9\n")?;
+        assert_eq!(read_ids(absent_file.path())?, vec![3, 7, 9]);
+        absent_file.write_str("3,x")?;
+        assert!(read_ids(absent_file.path()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pair_with_absent() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = assert_fs::NamedTempFile::new("people.csv")?;
+        input_file.write_str("1,Alice\n2,Bob\n3,Charlie\n4,David\n")?;
+
+        let config = PairingConfig::new().with_absent([2, 3]);
+        pair(input_file.path(), temp_dir.path(), &config, 1)?;
+
+        let history = History::from_dir(&temp_dir.path().to_string_lossy())?;
+        assert_eq!(history.get((1, 4)), Some(1));
+        assert_eq!(history.stats().pairs, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pair_with_exclusions() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = assert_fs::NamedTempFile::new("people.csv")?;
+        input_file.write_str("1,Alice\n2,Bob\n3,Charlie\n4,David\n")?;
+        let exclusions_file = assert_fs::NamedTempFile::new("exclusions.csv")?;
+        exclusions_file.write_str("1,2\n1,3\n1,4\n")?;
+
+        let f = File::open(exclusions_file.path())?;
+        let config = PairingConfig::new().with_exclusions(Exclusions::from_csv(f)?);
+        // Alice can't be paired with anyone
+        let result = pair(input_file.path(), temp_dir.path(), &config, 1);
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pair_alternatives() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = assert_fs::NamedTempFile::new("people.csv")?;
+        input_file.write_str("1,Alice\n2,Bob\n3,Charlie\n4,David\n")?;
+
+        let args = ["app", "pair", "-i", "p.csv", "-o", "out"];
+        let cli = Cli::try_parse_from(args.iter().chain(&["--alternatives", "3"]))?;
+        match cli.command {
+            Commands::Pair { alternatives, .. } => assert_eq!(alternatives, 3),
+            _ => panic!("expected the pair command"),
+        }
+        let both = ["--alternatives", "3", "--pick", "2"];
+        assert!(Cli::try_parse_from(args.iter().chain(&both)).is_err());
+        assert!(Cli::try_parse_from(args.iter().chain(&["--pick", "0"])).is_err());
+
+        // showing the options doesn't save anything
+        let config = PairingConfig::new().with_solver(Solver::Exact);
+        show_alternatives(input_file.path(), temp_dir.path(), &config, 3)?;
+        assert_eq!(std::fs::read_dir(temp_dir.path())?.count(), 0);
+
+        // four people can only be paired three ways
+        assert!(pair(input_file.path(), temp_dir.path(), &config, 4).is_err());
+        pair(input_file.path(), temp_dir.path(), &config, 3)?;
+        let people = People::from_csv(File::open(input_file.path())?)?;
+        let third = buddy_up_lib::pair_alternatives(people, &History::default(), &config, 3)?
+            .remove(2)
+            .into_groups()
+            .inner();
+        let history = History::from_dir(&temp_dir.path().to_string_lossy())?;
+        assert_eq!(history.len(), 2);
+        for group in third {
+            assert_eq!(history.get((group[0].id, group[1].id)), Some(1));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pair_with_cooldown() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = assert_fs::NamedTempFile::new("people.csv")?;
+        input_file.write_str("1,Alice\n2,Bob\n3,Charlie\n4,David\n")?;
+
+        let args = ["app", "pair", "-i", "p.csv", "-o", "out"];
+        let cli = Cli::try_parse_from(args.iter().chain(&["--cooldown", "3"]))?;
+        match cli.command {
+            Commands::Pair { pairing, .. } => assert_eq!(pairing.cooldown, Some(3)),
+            _ => panic!("expected the pair command"),
+        }
+
+        // four people only have three rounds of new pairs in them
+        let rounds = [[(1, 2), (3, 4)], [(1, 3), (2, 4)], [(1, 4), (2, 3)]];
+        for (n, round) in rounds.iter().enumerate() {
+            let groups: Vec<String> = round
+                .iter()
+                .map(|(a, b)| {
+                    format!(r#"[{{"id": {a}, "name": "A"}}, {{"id": {b}, "name": "B"}}]"#)
+                })
+                .collect();
+            let file = temp_dir.path().join(format!("20250101_00000{n}.json"));
+            std::fs::write(file, format!("[{}]", groups.join(",")))?;
+        }
+        let config = PairingConfig::new().with_cooldown(3);
+        assert!(pair(input_file.path(), temp_dir.path(), &config, 1).is_err());
+
+        // the first round is out of the cooldown, so its pairs are fine again
+        let config = PairingConfig::new().with_cooldown(2);
+        pair(input_file.path(), temp_dir.path(), &config, 1)?;
+        let history = History::from_dir(&temp_dir.path().to_string_lossy())?;
+        assert_eq!(history.get((1, 2)), Some(2));
+        assert_eq!(history.get((3, 4)), Some(2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reroll() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = assert_fs::NamedTempFile::new("people.csv")?;
+        input_file.write_str("1,Alice\n2,Bob\n3,Charlie\n4,David\n5,Eve\n6,Frank\n")?;
+
+        let args = ["app", "reroll", "-i", "p.csv", "-o", "out", "--keep", "1,2"];
+        let cli = Cli::try_parse_from(args.iter().chain(&["--cooldown", "2", "--mentoring"]))?;
+        match cli.command {
+            Commands::Reroll { keep, pairing, .. } => {
+                assert_eq!(keep, vec![(1, 2)]);
+                // the same rules as pairing
+                let config = pairing.config()?;
+                assert_eq!(config.cooldown(), 2);
+                assert!(config.mentoring());
+            }
+            _ => panic!("expected the reroll command"),
+        }
+        assert!(Cli::try_parse_from(["app", "reroll", "-i", "p", "-o", "o", "-k", "1"]).is_err());
+
+        let config = PairingConfig::new().with_solver(Solver::Exact);
+        assert!(reroll(input_file.path(), temp_dir.path(), &config, &[]).is_err());
+
+        let round = r#"[[{"id": 1, "name": "Alice"}, {"id": 2, "name": "Bob"}],
+            [{"id": 3, "name": "Charlie"}, {"id": 4, "name": "David"}],
+            [{"id": 5, "name": "Eve"}, {"id": 6, "name": "Frank"}]]"#;
+        std::fs::write(temp_dir.path().join("20250101_000000.json"), round)?;
+        // a failed re-roll leaves the old round alone
+        assert!(reroll(input_file.path(), temp_dir.path(), &config, &[(1, 3)]).is_err());
+        assert!(temp_dir.path().join("20250101_000000.json").exists());
+        reroll(input_file.path(), temp_dir.path(), &config, &[(1, 2)])?;
+
+        // the new round took the old one's place
+        let dir = temp_dir.path().to_string_lossy();
+        let history = History::from_dir(&dir)?;
+        assert_eq!(history.stats().files_read, 1);
+        assert_eq!(history.get((1, 2)), Some(1));
+        assert_eq!(history.get((3, 4)), None);
+        assert_eq!(history.get((5, 6)), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pair_with_preferences() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = assert_fs::NamedTempFile::new("people.csv")?;
+        input_file.write_str("1,Alice\n2,Bob\n3,Charlie\n4,David\n")?;
+        let preferences_file = assert_fs::NamedTempFile::new("preferences.csv")?;
+        preferences_file.write_str("1,4,1\n4,1,1\n2,3,0.5\n")?;
+
+        let args = ["app", "pair", "-i", "p.csv", "-o", "out"];
+        let cli = Cli::try_parse_from(args.iter().chain(&[
+            "--preferences",
+            "prefs.csv",
+            "--preference-weight",
+            "0.4",
+        ]))?;
+        match cli.command {
+            Commands::Pair { pairing, .. } => assert_eq!(pairing.preference_weight, 0.4),
+            _ => panic!("expected the pair command"),
+        }
+        assert!(Cli::try_parse_from(args.iter().chain(&["--preference-weight", "0.4"])).is_err());
+
+        let f = File::open(preferences_file.path())?;
+        let config = PairingConfig::new().with_preferences(Preferences::from_csv(f)?, 0.25);
+        pair(input_file.path(), temp_dir.path(), &config, 1)?;
+
+        let history = History::from_dir(&temp_dir.path().to_string_lossy())?;
+        assert_eq!(history.get((1, 4)), Some(1));
+        assert_eq!(history.get((2, 3)), Some(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pair_with_pins() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = assert_fs::NamedTempFile::new("people.csv")?;
+        input_file.write_str("1,Alice\n2,Bob\n3,Charlie\n4,David\n")?;
+        let pins_file = assert_fs::NamedTempFile::new("pins.csv")?;
+        pins_file.write_str("1,2\n")?;
+
+        let f = File::open(pins_file.path())?;
+        let config = PairingConfig::new().with_pins(Pins::from_csv(f)?);
+        pair(input_file.path(), temp_dir.path(), &config, 1)?;
+
+        // the pinned pair ends up in the history
+        let history = History::from_dir(&temp_dir.path().to_string_lossy())?;
+        assert_eq!(history.get((1, 2)), Some(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_plan() {
+        let cli = Cli::try_parse_from([
+            "app",
+            "plan",
+            "-i",
+            "people.csv",
+            "-o",
+            "output_dir",
+            "--rounds",
+            "5",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Plan {
+                rounds, lookahead, ..
+            } => {
+                assert_eq!(rounds, 5);
+                assert!(!lookahead);
+            }
+            _ => panic!("expected the plan command"),
+        }
+
+        let cli = Cli::try_parse_from([
+            "app",
+            "plan",
+            "-i",
+            "people.csv",
+            "-o",
+            "output_dir",
+            "-r",
+            "5",
+            "--lookahead",
+            "--cooldown",
+            "2",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Plan {
+                lookahead, pairing, ..
+            } => {
+                assert!(lookahead);
+                assert_eq!(pairing.cooldown, Some(2));
+            }
+            _ => panic!("expected the plan command"),
+        }
+    }
+
+    #[test]
+    fn test_plan_function() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = assert_fs::NamedTempFile::new("people.csv")?;
+        input_file.write_str("1,Alice\n2,Bob\n3,Charlie\n4,David\n")?;
+
+        plan(input_file.path(), temp_dir.path(), None, 3)?;
+        let config = PairingConfig::default().with_seed(7);
+        plan(input_file.path(), temp_dir.path(), Some(&config), 3)?;
+
+        // planning doesn't touch the history
+        assert_eq!(std::fs::read_dir(temp_dir.path())?.count(), 0);
+        Ok(())
+    }
+
     #[test]
     fn test_initialize_logging() {
         // Simply verify it doesn't panic
@@ -160,17 +1227,43 @@ mod tests {
     #[test]
     fn test_pair_with_nonexistent_input() {
         let temp_dir = TempDir::new().unwrap();
-        let result = pair(Path::new("/nonexistent/file.csv"), temp_dir.path());
+        let result = pair(
+            Path::new("/nonexistent/file.csv"),
+            temp_dir.path(),
+            &PairingConfig::default(),
+            1,
+        );
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_pair_with_too_few_people() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = assert_fs::NamedTempFile::new("people.csv")?;
+        let config = PairingConfig::default();
+
+        input_file.write_str("")?;
+        let err = pair(input_file.path(), temp_dir.path(), &config, 1).unwrap_err();
+        assert!(err.to_string().contains("nobody to pair"), "{err}");
+
+        input_file.write_str("1,Alice\n")?;
+        let err = pair(input_file.path(), temp_dir.path(), &config, 1).unwrap_err();
+        assert!(err.to_string().contains("only 1"), "{err}");
+        Ok(())
+    }
+
     #[test]
     fn test_pair_with_invalid_csv() -> Result<()> {
         let temp_dir = TempDir::new()?;
         let input_file = assert_fs::NamedTempFile::new("invalid.csv")?;
         input_file.write_str("invalid csv content")?;
 
-        let result = pair(input_file.path(), temp_dir.path());
+        let result = pair(
+            input_file.path(),
+            temp_dir.path(),
+            &PairingConfig::default(),
+            1,
+        );
         assert!(result.is_err());
 
         Ok(())
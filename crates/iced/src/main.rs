@@ -1,9 +1,19 @@
 use arboard::Clipboard;
-use buddy_up_lib::{History, People, pair, print_table, save_history};
-use iced::widget::{button, column, container, text};
+use buddy_up_lib::{
+    BuddyError, CancelToken, GeneticParams, History, PairingConfig, PairingOutcome, People,
+    Progress, pair_alternatives, print_table, reroll_groups, save_history,
+};
+use iced::futures::channel::mpsc;
+use iced::widget::{button, checkbox, column, container, row, scrollable, text, text_input};
 use iced::{Element, Task};
 use rfd::FileDialog;
+use std::collections::BTreeSet;
 use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How often the pairing thread sends progress, so the window doesn't redraw for every generation.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
 
 #[derive(Debug, Clone)]
 pub enum Message {
@@ -16,6 +26,28 @@ pub enum Message {
     FileSelected(Option<PathBuf>),
     DirectorySelected(Option<PathBuf>),
     CopyToClipboard,
+    PopulationSizeChanged(String),
+    StaleGenerationsChanged(String),
+    MutationRateChanged(String),
+    CrossoverRateChanged(String),
+    ReplacementRateChanged(String),
+    ElitismRateChanged(String),
+    TimeLimitChanged(String),
+    PresentToggled(usize, bool),
+    Pairing(PairingEvent),
+    CancelPairing,
+    AlternativesChanged(String),
+    ShowAlternative(usize),
+    SaveGroups,
+    KeepToggled(usize, bool),
+    Reroll,
+}
+
+/// News from the pairing thread.
+#[derive(Debug, Clone)]
+pub enum PairingEvent {
+    Progress(Progress),
+    Done(Result<Vec<PairingOutcome>, String>),
 }
 
 pub struct App {
@@ -27,6 +59,23 @@ pub struct App {
     history_status: String,
     pairs_output: String,
     clipboard_message: String,
+    population_size: String,
+    stale_generations: String,
+    mutation_rate: String,
+    crossover_rate: String,
+    replacement_rate: String,
+    elitism_rate: String,
+    time_limit: String,
+    alternatives: String,
+    absent: BTreeSet<usize>,
+    /// The groupings to choose from, best first, until one of them is saved.
+    outcomes: Vec<PairingOutcome>,
+    shown: usize,
+    /// Which groups of the shown option to keep when re-rolling the rest.
+    kept: BTreeSet<usize>,
+    /// Set while pairing runs in the background, to stop it early.
+    running: Option<CancelToken>,
+    progress: String,
 }
 
 impl Default for App {
@@ -40,6 +89,20 @@ impl Default for App {
             history_status: "No history loaded".to_string(),
             pairs_output: String::new(),
             clipboard_message: String::new(),
+            population_size: PairingConfig::default().population_size().to_string(),
+            stale_generations: PairingConfig::default().max_stale_generations().to_string(),
+            mutation_rate: PairingConfig::default().mutation_rate().to_string(),
+            crossover_rate: PairingConfig::default().crossover_rate().to_string(),
+            replacement_rate: PairingConfig::default().elite_selection().0.to_string(),
+            elitism_rate: PairingConfig::default().elite_selection().1.to_string(),
+            time_limit: String::new(),
+            alternatives: "3".to_string(),
+            absent: BTreeSet::new(),
+            outcomes: Vec::new(),
+            shown: 0,
+            kept: BTreeSet::new(),
+            running: None,
+            progress: String::new(),
         }
     }
 }
@@ -53,6 +116,127 @@ impl App {
     //     "Buddy Up".to_string()
     // }
 
+    /// The pairing settings from the effort inputs.
+    fn config(&self) -> Result<PairingConfig, String> {
+        // no time limit unless there's one filled in
+        let time_limit = if self.time_limit.trim().is_empty() {
+            None
+        } else {
+            let seconds = self
+                .time_limit
+                .trim()
+                .parse()
+                .ok()
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                .ok_or_else(|| format!("'{}' is not a valid time limit", self.time_limit))?;
+            Some(seconds)
+        };
+        let params = GeneticParams {
+            population_size: Some(parse(&self.population_size, "population size")?),
+            max_stale_generations: Some(parse(&self.stale_generations, "number of generations")?),
+            mutation_rate: Some(parse(&self.mutation_rate, "mutation rate")?),
+            crossover_rate: Some(parse(&self.crossover_rate, "crossover rate")?),
+            elite_selection: Some((
+                parse(&self.replacement_rate, "replacement rate")?,
+                parse(&self.elitism_rate, "elitism rate")?,
+            )),
+            time_limit,
+        };
+        Ok(PairingConfig::default()
+            .with_genetic_params(params)
+            .with_absent(self.absent.iter().copied()))
+    }
+
+    /// How many groupings to offer, from the alternatives input.
+    fn alternatives(&self) -> Result<usize, String> {
+        match self.alternatives.trim().parse() {
+            Ok(count) if count > 0 => Ok(count),
+            _ => Err(format!(
+                "'{}' is not a valid number of alternatives",
+                self.alternatives
+            )),
+        }
+    }
+
+    /// Shows one of the groupings to choose from.
+    fn show(&mut self, n: usize) {
+        let Some(outcome) = self.outcomes.get(n) else {
+            return;
+        };
+        self.shown = n;
+        self.kept.clear();
+        self.clipboard_message.clear();
+        self.pairs_output = format!(
+            "Option {} of {}\n{}\n\n{outcome}",
+            n + 1,
+            self.outcomes.len(),
+            print_table(outcome.groups().clone())
+        );
+    }
+
+    /// Saves the shown groups to the history. The options stay up if that fails, to try again.
+    fn save(&mut self) {
+        let Some(outcome) = self.outcomes.get(self.shown) else {
+            return;
+        };
+
+        // Save the history
+        match save_history(outcome.groups(), &self.output_dir) {
+            Ok(_) => {
+                let outcome = self.outcomes.swap_remove(self.shown);
+                self.outcomes.clear();
+                let summary = outcome.to_string();
+                let pairs = outcome.into_groups();
+
+                // Reload history after saving
+                match History::from_dir(&self.output_dir) {
+                    Ok(history) => {
+                        self.history = history;
+                        self.history_status =
+                            format!("✓ Found {} pairs in history", self.history.len());
+                    }
+                    Err(e) => {
+                        self.history_status = format!("✗ Error reloading history: {e}");
+                    }
+                }
+
+                // Generate output table, with a summary of how it went
+                self.pairs_output = format!("{}\n\n{summary}", print_table(pairs));
+            }
+            Err(e) => {
+                self.pairs_output = format!("✗ Error saving pairs: {e}");
+            }
+        }
+    }
+
+    /// Runs a pairing `job` on a thread of its own, so the window stays responsive. Progress and
+    /// the outcomes come back as [`Message::Pairing`].
+    fn run(
+        &mut self,
+        config: PairingConfig,
+        job: impl FnOnce(&PairingConfig) -> Result<Vec<PairingOutcome>, BuddyError> + Send + 'static,
+    ) -> Task<Message> {
+        let cancel = CancelToken::new();
+        let (sender, receiver) = mpsc::unbounded();
+        let progress = sender.clone();
+        let last = Mutex::new(Duration::ZERO);
+        let config = config.with_cancel(cancel.clone()).with_progress(move |p| {
+            let mut last = last.lock().unwrap();
+            if p.elapsed >= *last + PROGRESS_INTERVAL {
+                *last = p.elapsed;
+                let _ = progress.unbounded_send(PairingEvent::Progress(p));
+            }
+        });
+        std::thread::spawn(move || {
+            let outcomes = job(&config).map_err(|e| e.to_string());
+            let _ = sender.unbounded_send(PairingEvent::Done(outcomes));
+        });
+
+        self.running = Some(cancel);
+        self.progress = "Pairing...".to_string();
+        Task::run(receiver, Message::Pairing)
+    }
+
     fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::InputFileChanged(value) => {
@@ -86,6 +270,7 @@ impl App {
                         Ok(file) => match People::from_csv(file) {
                             Ok(people) => {
                                 self.people = people;
+                                self.absent.clear();
                                 self.people_status =
                                     format!("✓ Loaded {} people", self.people.len());
                             }
@@ -121,6 +306,7 @@ impl App {
                         Ok(file) => match People::from_csv(file) {
                             Ok(people) => {
                                 self.people = people;
+                                self.absent.clear();
                                 self.people_status =
                                     format!("✓ Loaded {} people", self.people.len());
                             }
@@ -134,34 +320,113 @@ impl App {
                     }
                 }
             }
+            Message::PopulationSizeChanged(value) => {
+                self.population_size = value;
+            }
+            Message::StaleGenerationsChanged(value) => {
+                self.stale_generations = value;
+            }
+            Message::MutationRateChanged(value) => {
+                self.mutation_rate = value;
+            }
+            Message::CrossoverRateChanged(value) => {
+                self.crossover_rate = value;
+            }
+            Message::ReplacementRateChanged(value) => {
+                self.replacement_rate = value;
+            }
+            Message::ElitismRateChanged(value) => {
+                self.elitism_rate = value;
+            }
+            Message::TimeLimitChanged(value) => {
+                self.time_limit = value;
+            }
+            Message::PresentToggled(id, present) => {
+                if present {
+                    self.absent.remove(&id);
+                } else {
+                    self.absent.insert(id);
+                }
+            }
             Message::GeneratePairs => {
-                if !self.people.is_empty() && !self.output_dir.is_empty() {
-                    let pairs = pair(self.people.clone(), &self.history);
-
-                    // Save the history
-                    match save_history(&pairs, &self.output_dir) {
-                        Ok(_) => {
-                            // Reload history after saving
-                            match History::from_dir(&self.output_dir) {
-                                Ok(history) => {
-                                    self.history = history;
-                                    self.history_status =
-                                        format!("✓ Found {} pairs in history", self.history.len());
-                                }
-                                Err(e) => {
-                                    self.history_status = format!("✗ Error reloading history: {e}");
-                                }
+                if !self.people.is_empty() && !self.output_dir.is_empty() && self.running.is_none()
+                {
+                    let (config, count) =
+                        match self.config().and_then(|c| Ok((c, self.alternatives()?))) {
+                            Ok(settings) => settings,
+                            Err(e) => {
+                                self.pairs_output = format!("✗ Error generating pairs: {e}");
+                                return Task::none();
                             }
+                        };
 
-                            // Generate output table
-                            self.pairs_output = print_table(pairs);
-                        }
-                        Err(_e) => {
-                            // error saving pairs, do nothing
+                    let people = self.people.clone();
+                    let history = self.history.clone();
+                    return self.run(config, move |config| {
+                        pair_alternatives(people, &history, config, count)
+                    });
+                }
+            }
+            Message::KeepToggled(n, keep) => {
+                if keep {
+                    self.kept.insert(n);
+                } else {
+                    self.kept.remove(&n);
+                }
+            }
+            Message::Reroll => {
+                if self.running.is_none()
+                    && let Some(outcome) = self.outcomes.get(self.shown)
+                {
+                    let config = match self.config() {
+                        Ok(config) => config,
+                        Err(e) => {
+                            self.pairs_output = format!("✗ Error re-rolling pairs: {e}");
+                            return Task::none();
                         }
+                    };
+                    let previous = outcome.groups().clone();
+                    let keep = self.kept.clone();
+                    let people = self.people.clone();
+                    let history = self.history.clone();
+                    return self.run(config, move |config| {
+                        reroll_groups(people, &history, config, &previous, &keep).map(|o| vec![o])
+                    });
+                }
+            }
+            Message::Pairing(PairingEvent::Progress(progress)) => {
+                self.progress = match progress.best_score {
+                    Some(score) => format!(
+                        "Generation {}, best score so far {score}",
+                        progress.generation
+                    ),
+                    None => format!("Generation {}", progress.generation),
+                };
+            }
+            Message::Pairing(PairingEvent::Done(outcome)) => {
+                self.running = None;
+                self.progress.clear();
+                match outcome {
+                    Ok(outcomes) => {
+                        self.outcomes = outcomes;
+                        self.show(0);
+                    }
+                    Err(e) => {
+                        self.pairs_output = format!("✗ Error generating pairs: {e}");
                     }
                 }
             }
+            Message::AlternativesChanged(value) => {
+                self.alternatives = value;
+            }
+            Message::ShowAlternative(n) => self.show(n),
+            Message::SaveGroups => self.save(),
+            Message::CancelPairing => {
+                if let Some(cancel) = &self.running {
+                    cancel.cancel();
+                    self.progress = "Cancelling...".to_string();
+                }
+            }
             Message::CopyToClipboard => {
                 if !self.pairs_output.is_empty() {
                     match Clipboard::new() {
@@ -191,13 +456,112 @@ impl App {
         ]
         .spacing(5);
 
+        // everyone is checked by default, uncheck whoever is away this round
+        let presence = self
+            .people
+            .iter()
+            .fold(column![].spacing(2), |col, (id, name)| {
+                col.push(
+                    checkbox(!self.absent.contains(&id))
+                        .label(name.to_string())
+                        .text_size(12)
+                        .size(12)
+                        .on_toggle(move |present| Message::PresentToggled(id, present)),
+                )
+            });
+        let input_section = if self.people.is_empty() {
+            input_section
+        } else {
+            input_section.push(scrollable(presence).height(120))
+        };
+
         let output_section = column![
             text("History:").size(20),
             button("Pick Directory").on_press(Message::PickOutputDir),
         ]
         .spacing(5);
 
-        let controls = column![if !self.people.is_empty() && !self.output_dir.is_empty() {
+        let effort_section = column![
+            text("Effort:").size(20),
+            row![
+                text("Population").size(12).width(120),
+                text_input("50", &self.population_size)
+                    .on_input(Message::PopulationSizeChanged)
+                    .size(12),
+            ]
+            .spacing(5)
+            .align_y(iced::Alignment::Center),
+            row![
+                text("Stale generations").size(12).width(120),
+                text_input("1000", &self.stale_generations)
+                    .on_input(Message::StaleGenerationsChanged)
+                    .size(12),
+            ]
+            .spacing(5)
+            .align_y(iced::Alignment::Center),
+            row![
+                text("Mutation rate").size(12).width(120),
+                text_input("0.2", &self.mutation_rate)
+                    .on_input(Message::MutationRateChanged)
+                    .size(12),
+            ]
+            .spacing(5)
+            .align_y(iced::Alignment::Center),
+            row![
+                text("Crossover rate").size(12).width(120),
+                text_input("0.9", &self.crossover_rate)
+                    .on_input(Message::CrossoverRateChanged)
+                    .size(12),
+            ]
+            .spacing(5)
+            .align_y(iced::Alignment::Center),
+            row![
+                text("Replacement rate").size(12).width(120),
+                text_input("0.5", &self.replacement_rate)
+                    .on_input(Message::ReplacementRateChanged)
+                    .size(12),
+            ]
+            .spacing(5)
+            .align_y(iced::Alignment::Center),
+            row![
+                text("Elitism rate").size(12).width(120),
+                text_input("0.5", &self.elitism_rate)
+                    .on_input(Message::ElitismRateChanged)
+                    .size(12),
+            ]
+            .spacing(5)
+            .align_y(iced::Alignment::Center),
+            row![
+                text("Alternatives").size(12).width(120),
+                text_input("3", &self.alternatives)
+                    .on_input(Message::AlternativesChanged)
+                    .size(12),
+            ]
+            .spacing(5)
+            .align_y(iced::Alignment::Center),
+            row![
+                text("Time limit (s)").size(12).width(120),
+                text_input("none", &self.time_limit)
+                    .on_input(Message::TimeLimitChanged)
+                    .size(12),
+            ]
+            .spacing(5)
+            .align_y(iced::Alignment::Center),
+        ]
+        .spacing(5);
+
+        let controls = column![if self.running.is_some() {
+            column![
+                text("Results:").size(20),
+                row![
+                    button("Generate Pairs"),
+                    button("Cancel").on_press(Message::CancelPairing)
+                ]
+                .spacing(5),
+                text(&self.progress).size(12),
+            ]
+            .spacing(5)
+        } else if !self.people.is_empty() && !self.output_dir.is_empty() {
             column![
                 text("Results:").size(20),
                 button("Generate Pairs").on_press(Message::GeneratePairs)
@@ -207,8 +571,56 @@ impl App {
         },]
         .spacing(10);
 
+        // flip through the options until one is saved
+        let choices = if self.outcomes.is_empty() {
+            row![]
+        } else {
+            let previous = self.shown.checked_sub(1).map(Message::ShowAlternative);
+            let next = (self.shown + 1 < self.outcomes.len())
+                .then_some(Message::ShowAlternative(self.shown + 1));
+            row![
+                button("Previous").on_press_maybe(previous),
+                button("Next").on_press_maybe(next),
+                button("Save to History").on_press(Message::SaveGroups),
+            ]
+            .spacing(5)
+        };
+
+        // tick the groups that work, and re-roll the others
+        let keepers = match self.outcomes.get(self.shown) {
+            Some(outcome) if self.running.is_none() => {
+                let groups = outcome.groups().clone().inner();
+                let keepers =
+                    groups
+                        .into_iter()
+                        .enumerate()
+                        .fold(column![].spacing(2), |col, (n, group)| {
+                            let names: Vec<String> = group
+                                .iter()
+                                .filter(|p| p.id != usize::MAX)
+                                .map(|p| p.to_string())
+                                .collect();
+                            col.push(
+                                checkbox(self.kept.contains(&n))
+                                    .label(format!("Keep {}", names.join(" & ")))
+                                    .text_size(12)
+                                    .size(12)
+                                    .on_toggle(move |keep| Message::KeepToggled(n, keep)),
+                            )
+                        });
+                column![
+                    scrollable(keepers).height(120),
+                    button("Re-roll Others").on_press(Message::Reroll),
+                ]
+                .spacing(5)
+            }
+            _ => column![],
+        };
+
         let results_section = if !self.pairs_output.is_empty() {
             column![
+                choices,
+                keepers,
                 button("Copy to Clipboard").on_press(Message::CopyToClipboard),
                 if !self.clipboard_message.is_empty() {
                     text(&self.clipboard_message).size(12).color(
@@ -237,9 +649,15 @@ impl App {
             column![]
         };
 
-        let left_column = column![input_section, output_section, controls, results_section,]
-            .spacing(20)
-            .width(iced::Length::FillPortion(2));
+        let left_column = column![
+            input_section,
+            output_section,
+            effort_section,
+            controls,
+            results_section,
+        ]
+        .spacing(20)
+        .width(iced::Length::FillPortion(2));
 
         let right_column = column![
             text("Status").size(20),
@@ -285,6 +703,14 @@ impl App {
     }
 }
 
+/// Parses one of the effort inputs, saying which one was off if it doesn't.
+fn parse<T: std::str::FromStr>(input: &str, what: &str) -> Result<T, String> {
+    input
+        .trim()
+        .parse()
+        .map_err(|_| format!("'{input}' is not a valid {what}"))
+}
+
 fn main() -> iced::Result {
     // Use the new iced::application API: provide the boot, update and view
     // functions directly. Set a static title via `title` and then run the
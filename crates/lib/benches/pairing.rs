@@ -0,0 +1,92 @@
+use buddy_up_lib::bench::{HistoryFitness, MatrixFitness};
+use buddy_up_lib::{History, PairingConfig, People, Solver, pair, plan_schedule};
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+use tempfile::TempDir;
+
+/// A roster of `n` people who all met each other at least once, and some twice, like a big team
+/// with a long history. There's no perfect score to find, so the genetic algorithm keeps going
+/// until it goes stale, and most of the time goes into scoring chromosomes.
+fn roster(n: usize) -> (People, History) {
+    let csv: Vec<String> = (1..=n).map(|i| format!("{i},P{i}")).collect();
+    let people = People::from_csv(csv.join("\n").as_bytes()).unwrap();
+
+    let dir = TempDir::new().unwrap();
+    let rounds = plan_schedule(people.clone(), &History::default(), n + n / 10).unwrap();
+    for (n, round) in rounds.iter().enumerate() {
+        let json = serde_json::to_string(round).unwrap();
+        let name = format!(
+            "20250101_{:02}{:02}{:02}.json",
+            n / 3600,
+            n / 60 % 60,
+            n % 60
+        );
+        std::fs::write(dir.path().join(name), json).unwrap();
+    }
+    let history = History::from_dir(&dir.path().to_string_lossy()).unwrap();
+    (people, history)
+}
+
+/// Scoring one chromosome, which is what the genetic algorithm spends its time on: looking every
+/// pair up in the history, like it used to, against the cost matrix it uses now.
+fn fitness(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fitness");
+    for n in [100, 500] {
+        let (people, history) = roster(n);
+        let config = PairingConfig::new();
+        let ids: Vec<usize> = people.iter().map(|(id, _)| id).collect();
+        let positions: Vec<usize> = (0..ids.len()).collect();
+
+        let baseline = HistoryFitness::new(&people, &history, &config);
+        group.bench_with_input(BenchmarkId::new("history", n), &n, |b, _| {
+            b.iter(|| baseline.score(black_box(&ids)));
+        });
+        let matrix = MatrixFitness::new(&people, &history, &config);
+        group.bench_with_input(BenchmarkId::new("matrix", n), &n, |b, _| {
+            b.iter(|| matrix.score(black_box(&positions)));
+        });
+    }
+    group.finish();
+}
+
+fn genetic(c: &mut Criterion) {
+    let mut group = c.benchmark_group("genetic");
+    group.sample_size(10);
+    for n in [100, 500] {
+        let (people, history) = roster(n);
+        let config = PairingConfig::new().with_seed(1);
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, _| {
+            b.iter(|| pair(black_box(people.clone()), &history, &config).unwrap());
+        });
+    }
+    group.finish();
+}
+
+fn exact(c: &mut Criterion) {
+    let mut group = c.benchmark_group("exact");
+    group.sample_size(10);
+    for n in [100, 500] {
+        let (people, history) = roster(n);
+        let config = PairingConfig::new().with_solver(Solver::Exact);
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, _| {
+            b.iter(|| pair(black_box(people.clone()), &history, &config).unwrap());
+        });
+    }
+    group.finish();
+}
+
+fn heuristic(c: &mut Criterion) {
+    let mut group = c.benchmark_group("heuristic");
+    group.sample_size(10);
+    for n in [100, 500, 2000] {
+        let (people, history) = roster(n);
+        let config = PairingConfig::new().with_solver(Solver::Heuristic);
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, _| {
+            b.iter(|| pair(black_box(people.clone()), &history, &config).unwrap());
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, fitness, genetic, exact, heuristic);
+criterion_main!(benches);
@@ -0,0 +1,54 @@
+//! Hooks for the benchmarks, which only get to see the public API. Only built with the `bench`
+//! feature, which the benchmarks turn on for themselves.
+
+use super::cost::{CostMatrix, Costs};
+use super::{PairingConfig, group_sizes, split};
+use crate::History;
+use crate::People;
+
+/// Scores chromosomes the way the genetic solver used to, with the genes being IDs and every pair
+/// looked up in the [`History`]. The baseline for [`MatrixFitness`].
+pub struct HistoryFitness {
+    costs: Costs,
+    sizes: Vec<usize>,
+}
+
+impl HistoryFitness {
+    pub fn new(people: &People, last: &History, config: &PairingConfig) -> Self {
+        Self {
+            costs: Costs::new(people, last, config),
+            sizes: group_sizes(people.len(), config.group_size),
+        }
+    }
+
+    /// The score of a chromosome of everyone's IDs.
+    pub fn score(&self, genes: &[usize]) -> isize {
+        split(genes, &self.sizes)
+            .map(|group| self.costs.group(group))
+            .sum()
+    }
+}
+
+/// Scores chromosomes the way the genetic solver does now, with the genes being positions in the
+/// roster and every pair looked up in a [`CostMatrix`].
+pub struct MatrixFitness {
+    costs: CostMatrix,
+    sizes: Vec<usize>,
+}
+
+impl MatrixFitness {
+    pub fn new(people: &People, last: &History, config: &PairingConfig) -> Self {
+        let ids: Vec<usize> = people.iter().map(|(id, _)| id).collect();
+        Self {
+            costs: CostMatrix::new(&ids, &Costs::new(people, last, config)),
+            sizes: group_sizes(ids.len(), config.group_size),
+        }
+    }
+
+    /// The score of a chromosome of everyone's positions in [`People::iter`].
+    pub fn score(&self, genes: &[usize]) -> isize {
+        split(genes, &self.sizes)
+            .map(|group| self.costs.group(group))
+            .sum()
+    }
+}
@@ -0,0 +1,460 @@
+use super::Decay;
+use super::PairingConfig;
+use super::overlap::{self, WorkingHours};
+use crate::History;
+use crate::People;
+use chrono::NaiveDateTime;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tracing::trace;
+
+/// Added to the score for every pair in a grouping that must never meet. Big enough that any
+/// grouping without forbidden pairs scores better than one with them.
+pub(crate) const FORBIDDEN: isize = 1_000_000_000;
+
+/// Added to the score for every pair that was just turned down. Far more than any history adds up
+/// to, so they only meet again when there's no one else, but less than a pair that must never meet.
+pub(crate) const REJECTED: isize = 1_000_000;
+
+/// Fitness scores are whole numbers, so fractional costs get scaled up by this much and rounded.
+pub(crate) const SCALE: f64 = 1000.0;
+
+/// Everything that decides how good or bad it is to put two people together. Lower is better.
+#[derive(Clone, Debug)]
+pub(crate) struct Costs {
+    last: History,
+    config: PairingConfig,
+    /// The time decay is measured against: the newest meeting in the history, not the clock, so
+    /// the same history always scores the same.
+    now: NaiveDateTime,
+    /// For every diversity attribute, each person's value and what sharing it costs.
+    diversity: Vec<(HashMap<usize, String>, f64)>,
+    /// Kept around for any custom scorers to look things up in.
+    people: People,
+    /// Who may be paired with the evenizer and sit this round out. Only those with the fewest byes
+    /// so far, so nobody sits out twice before everyone sat out once.
+    bye_eligible: HashSet<usize>,
+    /// Everyone's working hours in UTC, if we need them for the minimum overlap.
+    hours: HashMap<usize, WorkingHours>,
+    /// The best mutual preference of any pair, which costs nothing extra.
+    best_preference: f64,
+    /// Pairs that met too recently to meet again, going by the cooldown.
+    cooling: HashSet<(usize, usize)>,
+    /// Pairs ruled out on top of the config, like while looking for alternatives.
+    forbidden: HashSet<(usize, usize)>,
+    /// Pairs that were just tried and turned down, like when re-rolling.
+    rejected: HashSet<(usize, usize)>,
+}
+
+impl Costs {
+    pub(crate) fn new(people: &People, last: &History, config: &PairingConfig) -> Self {
+        let diversity = config
+            .diversity
+            .iter()
+            .map(|(key, weight)| {
+                let values = people
+                    .as_ids()
+                    .into_iter()
+                    .filter_map(|id| Some((id, people.attribute(id, key)?.to_string())))
+                    .collect();
+                (values, *weight)
+            })
+            .collect();
+        // pinned people are busy, so they can't take the bye either
+        let free: Vec<usize> = people
+            .as_ids()
+            .into_iter()
+            .filter(|&id| id != usize::MAX && !config.pins.iter().any(|&(a, b)| id == a || id == b))
+            .collect();
+        let fewest = free.iter().map(|&id| last.byes(id)).min().unwrap_or(0);
+        let bye_eligible = free
+            .into_iter()
+            .filter(|&id| last.byes(id) == fewest)
+            .collect();
+        // checked up front by whoever pairs people, so there's nothing to report here
+        let hours = if config.min_overlap.is_some() {
+            overlap::working_hours(people).unwrap_or_default()
+        } else {
+            HashMap::new()
+        };
+        Self {
+            last: last.clone(),
+            config: config.clone(),
+            now: last.latest().unwrap_or_default(),
+            diversity,
+            people: people.clone(),
+            bye_eligible,
+            hours,
+            best_preference: config.preferences.best(),
+            cooling: HashSet::new(),
+            forbidden: HashSet::new(),
+            rejected: HashSet::new(),
+        }
+        .with_cooldown_from(last)
+    }
+
+    /// Takes the pairs on cooldown from `last` instead of the history the costs were made with.
+    /// Handy when that history has made-up rounds added to it.
+    pub(crate) fn with_cooldown_from(mut self, last: &History) -> Self {
+        // sitting out isn't meeting anyone, the bye rotation takes care of that
+        self.cooling = last
+            .met_within(self.config.cooldown)
+            .filter(|&(_, j)| j != usize::MAX)
+            .collect();
+        self
+    }
+
+    /// Rules out putting `i` and `j` together.
+    pub(crate) fn forbid(&mut self, i: usize, j: usize) {
+        self.forbidden.insert((i.min(j), i.max(j)));
+    }
+
+    /// Makes `i` and `j` meeting again a last resort, without touching the history.
+    pub(crate) fn reject(&mut self, i: usize, j: usize) {
+        self.rejected.insert((i.min(j), i.max(j)));
+    }
+
+    /// How many times `id` sat a round out so far.
+    pub(crate) fn byes(&self, id: usize) -> usize {
+        self.last.byes(id)
+    }
+
+    /// Whether any pairs must never meet, or the costs are just preferences. Someone who can't take
+    /// the bye must never meet the evenizer, too.
+    pub(crate) fn has_forbidden(&self) -> bool {
+        !self.config.exclusions.is_empty()
+            || !self.cooling.is_empty()
+            || !self.forbidden.is_empty()
+            || !self.hours.is_empty()
+            || (self.people.has_evenizer()
+                && self
+                    .people
+                    .iter()
+                    .any(|(id, _)| !self.bye_eligible.contains(&id)))
+    }
+
+    /// The cost of putting `i` and `j` in the same group, or `None` if they must never meet.
+    #[allow(clippy::cast_possible_truncation)]
+    pub(crate) fn pair(&self, i: usize, j: usize) -> Option<isize> {
+        let key = (i.min(j), i.max(j));
+        if self.config.exclusions.contains(i, j)
+            || self.cooling.contains(&key)
+            || self.forbidden.contains(&key)
+        {
+            return None;
+        }
+        match (i, j) {
+            (id, usize::MAX) | (usize::MAX, id) if !self.bye_eligible.contains(&id) => return None,
+            _ => {}
+        }
+        if let (Some(min), Some(a), Some(b)) = (
+            self.config.min_overlap,
+            self.hours.get(&i),
+            self.hours.get(&j),
+        ) && i64::from(a.overlap(*b)) * 60 < min.as_secs() as i64
+        {
+            return None;
+        }
+        let last = self.met(i, j);
+        let same = self.same(i, j);
+        let custom = self.custom(i, j);
+        let preference = self.preference(i, j);
+        trace!(
+            "Found score {last}, {same} for shared attributes, {custom} from scorers and {preference} from preferences for pair ({i}, {j})."
+        );
+        let rejected = if self.rejected.contains(&key) {
+            REJECTED
+        } else {
+            0
+        };
+        // high score should be bad
+        Some(((last + same + custom + preference) * SCALE).round() as isize + rejected)
+    }
+
+    /// What the custom scorers think of `i` and `j` meeting, weighted.
+    fn custom(&self, i: usize, j: usize) -> f64 {
+        // nobody really meets the evenizer
+        if i == usize::MAX || j == usize::MAX {
+            return 0.0;
+        }
+        self.config
+            .scorers
+            .iter()
+            .map(|s| s.weight * s.scorer.score(&self.people, i, j).max(0.0))
+            .sum()
+    }
+
+    /// How far `i` and `j` are from the keenest pair, weighted.
+    fn preference(&self, i: usize, j: usize) -> f64 {
+        let mutual = self.config.preferences.mutual(i, j);
+        self.config.preference_weight * (self.best_preference - mutual)
+    }
+
+    /// The penalty for `i` and `j` sharing attributes we want diverse groups for.
+    fn same(&self, i: usize, j: usize) -> f64 {
+        self.diversity
+            .iter()
+            .filter(|(values, _)| matches!((values.get(&i), values.get(&j)), (Some(a), Some(b)) if a == b))
+            .map(|(_, weight)| weight)
+            .sum()
+    }
+
+    /// How often `i` and `j` met, with older meetings counting less depending on the [`Decay`].
+    #[allow(clippy::cast_precision_loss)]
+    fn met(&self, i: usize, j: usize) -> f64 {
+        match self.config.decay {
+            Decay::None => self.last.get((i, j)).unwrap_or(0) as f64,
+            Decay::HalfLife(half_life) => {
+                let half_life = half_life.as_secs_f64().max(1.0);
+                self.last
+                    .meetings((i, j))
+                    .iter()
+                    .map(|m| match m.at {
+                        Some(at) => {
+                            let age = (self.now - at).as_seconds_f64().max(0.0);
+                            0.5_f64.powf(age / half_life)
+                        }
+                        None => 1.0,
+                    })
+                    .sum()
+            }
+            Decay::Window(rounds) => {
+                let start = self.last.rounds().saturating_sub(rounds);
+                self.last
+                    .meetings((i, j))
+                    .iter()
+                    .filter(|m| m.round >= start)
+                    .count() as f64
+            }
+        }
+    }
+
+    /// The cost of a whole group, since everyone in a group meets everyone else. Forbidden pairs
+    /// count as [`FORBIDDEN`].
+    pub(crate) fn group(&self, group: &[usize]) -> isize {
+        let mut score = 0;
+        for (n, &i) in group.iter().enumerate() {
+            for &j in &group[n + 1..] {
+                score += self.pair(i, j).unwrap_or(FORBIDDEN);
+            }
+        }
+        score
+    }
+
+    /// Whether a group is free of forbidden pairs.
+    pub(crate) fn is_allowed(&self, group: &[usize]) -> bool {
+        group
+            .iter()
+            .enumerate()
+            .all(|(n, &i)| group[n + 1..].iter().all(|&j| self.pair(i, j).is_some()))
+    }
+}
+
+/// Every pair's cost from [`Costs::pair`], worked out once up front for a list of people. People
+/// go by their position in that list, so scoring a group is plain indexing instead of looking
+/// through the [`History`]. Forbidden pairs cost [`FORBIDDEN`]. Cheap to clone, so every thread
+/// can have its own.
+///
+/// Only one side of the diagonal is stored, in 32 bits per pair, so ten thousand people take
+/// about 200 MB.
+#[derive(Clone, Debug)]
+pub(crate) struct CostMatrix {
+    size: usize,
+    /// Row `i` holds the pairs `(i, j)` for every `j > i`.
+    costs: Arc<[i32]>,
+}
+
+impl CostMatrix {
+    pub(crate) fn new(ids: &[usize], costs: &Costs) -> Self {
+        let size = ids.len();
+        let mut matrix = Vec::with_capacity(size * size.saturating_sub(1) / 2);
+        for i in 0..size {
+            for j in i + 1..size {
+                let cost = costs.pair(ids[i], ids[j]).unwrap_or(FORBIDDEN);
+                // nothing but a forbidden pair should ever cost that much anyway
+                matrix.push(cost.clamp(-FORBIDDEN, FORBIDDEN) as i32);
+            }
+        }
+        Self {
+            size,
+            costs: matrix.into(),
+        }
+    }
+
+    /// The cost of putting the people at positions `i` and `j` together.
+    pub(crate) fn pair(&self, i: usize, j: usize) -> isize {
+        let (i, j) = (i.min(j), i.max(j));
+        if i == j {
+            return 0;
+        }
+        // the rows before `i` hold `size - 1`, `size - 2`, ... pairs
+        let row = i * (2 * self.size - i - 1) / 2;
+        self.costs[row + j - i - 1] as isize
+    }
+
+    /// What it costs for the person at position `i` to join a group, or to be in it. Being paired
+    /// with yourself is free, so it doesn't matter whether `i` is in there already.
+    pub(crate) fn joining(&self, i: usize, group: &[usize]) -> isize {
+        group.iter().map(|&j| self.pair(i, j)).sum()
+    }
+
+    /// Like [`Costs::group`], for a group of positions in the list of people.
+    pub(crate) fn group(&self, group: &[usize]) -> isize {
+        let mut score = 0;
+        for (n, &i) in group.iter().enumerate() {
+            for &j in &group[n + 1..] {
+                score += self.pair(i, j);
+            }
+        }
+        score
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Preferences;
+    use crate::algorithm::history::merge_at;
+    use chrono::TimeDelta;
+    use std::time::Duration;
+
+    #[test]
+    fn test_half_life() {
+        let mut history = History::default();
+        let now = NaiveDateTime::parse_from_str("20250601_090000", "%Y%m%d_%H%M%S").unwrap();
+        merge_at(&mut history, &[(1, 2)], Some(now - TimeDelta::days(30)));
+        merge_at(&mut history, &[(1, 3)], Some(now - TimeDelta::days(60)));
+        merge_at(&mut history, &[(1, 4)], None);
+        merge_at(&mut history, &[(5, 6)], Some(now));
+        let config = PairingConfig::new()
+            .with_decay(Decay::HalfLife(Duration::from_secs(30 * 24 * 60 * 60)));
+        // ages go by the newest round, however long ago that was
+        let costs = Costs::new(&People::default(), &history, &config);
+        assert_eq!(costs.now, now);
+        assert_eq!(costs.pair(5, 6), Some(1000));
+        assert_eq!(costs.pair(1, 2), Some(500));
+        assert_eq!(costs.pair(1, 3), Some(250));
+        // no timestamp, so it counts fully
+        assert_eq!(costs.pair(1, 4), Some(1000));
+        assert_eq!(costs.pair(2, 3), Some(0));
+    }
+
+    #[test]
+    fn test_window() {
+        let mut history = History::default();
+        for _ in 0..3 {
+            merge_at(&mut history, &[(1, 2)], None);
+        }
+        merge_at(&mut history, &[(1, 3)], None);
+        let people = People::default();
+        let config = PairingConfig::new().with_decay(Decay::Window(2));
+        let costs = Costs::new(&people, &history, &config);
+        assert_eq!(costs.pair(1, 2), Some(1000));
+        assert_eq!(costs.pair(1, 3), Some(1000));
+        let costs = Costs::new(&people, &history, &PairingConfig::new());
+        assert_eq!(costs.pair(1, 2), Some(3000));
+    }
+
+    #[test]
+    fn test_bye_rotation() {
+        let people = People::from_csv("1,A\n2,B\n3,C".as_bytes()).unwrap();
+        let mut history = History::default();
+        merge_at(&mut history, &[(1, 2), (3, usize::MAX)], None);
+        assert!(!Costs::new(&people, &History::default(), &PairingConfig::new()).has_forbidden());
+        let costs = Costs::new(&people, &history, &PairingConfig::new());
+        assert!(costs.has_forbidden());
+        assert_eq!(costs.pair(3, usize::MAX), None);
+        assert_eq!(costs.pair(usize::MAX, 1), Some(0));
+        assert_eq!(costs.pair(2, usize::MAX), Some(0));
+
+        // 1 is pinned, so only 2 is left to sit out
+        let mut pins = crate::Pins::new();
+        pins.insert(1, 3);
+        let costs = Costs::new(&people, &history, &PairingConfig::new().with_pins(pins));
+        assert_eq!(costs.pair(1, usize::MAX), None);
+        assert_eq!(costs.pair(2, usize::MAX), Some(0));
+    }
+
+    #[test]
+    fn test_diversity() {
+        let people = People::from_csv(
+            "1,A,team=X,office=B\n2,B,team=X,office=B\n3,C,team=Y\n4,D".as_bytes(),
+        )
+        .unwrap();
+        let mut history = History::default();
+        merge_at(&mut history, &[(1, 2)], None);
+        let config = PairingConfig::new()
+            .with_diversity("team", 1.0)
+            .with_diversity("office", 0.5);
+        let costs = Costs::new(&people, &history, &config);
+        // one repeat, same team and same office
+        assert_eq!(costs.pair(1, 2), Some(2500));
+        assert_eq!(costs.pair(1, 3), Some(0));
+        // people without the attribute never share it
+        assert_eq!(costs.pair(3, 4), Some(0));
+    }
+
+    #[test]
+    fn test_cooldown() {
+        let mut history = History::default();
+        merge_at(&mut history, &[(1, 2), (3, usize::MAX)], None);
+        merge_at(&mut history, &[(1, 3), (2, usize::MAX)], None);
+        merge_at(&mut history, &[(1, 4)], None);
+        let config = PairingConfig::new().with_cooldown(2);
+        let costs = Costs::new(&People::default(), &history, &config);
+        assert_eq!(costs.pair(1, 2), Some(1000));
+        assert_eq!(costs.pair(3, 1), None);
+        assert_eq!(costs.pair(1, 4), None);
+        assert!(costs.has_forbidden());
+
+        // without the made-up last round, 1 and 2 are within the cooldown again
+        let mut earlier = History::default();
+        merge_at(&mut earlier, &[(1, 2)], None);
+        let costs = costs.with_cooldown_from(&earlier);
+        assert_eq!(costs.pair(1, 2), None);
+        assert_eq!(costs.pair(1, 4), Some(1000));
+    }
+
+    #[test]
+    fn test_preferences() {
+        let mut preferences = Preferences::new();
+        preferences.insert(1, 2, 1.0);
+        preferences.insert(2, 1, 1.0);
+        preferences.insert(3, 4, -1.0);
+        let mut history = History::default();
+        merge_at(&mut history, &[(1, 2)], None);
+        let config = PairingConfig::new().with_preferences(preferences, 0.4);
+        let costs = Costs::new(&People::default(), &history, &config);
+        // the keenest pair only pays for their repeat
+        assert_eq!(costs.pair(1, 2), Some(1000));
+        assert_eq!(costs.pair(1, 3), Some(400));
+        assert_eq!(costs.pair(3, 4), Some(600));
+    }
+
+    #[test]
+    fn test_cost_matrix() {
+        let mut history = History::default();
+        merge_at(&mut history, &[(1, 2), (3, 4)], None);
+        merge_at(&mut history, &[(1, 2), (3, 5)], None);
+        let mut costs = Costs::new(&People::default(), &history, &PairingConfig::new());
+        costs.forbid(4, 5);
+        let ids = [5, 1, 3, 2, 4];
+        let matrix = CostMatrix::new(&ids, &costs);
+        // positions, not IDs
+        assert_eq!(matrix.group(&[1, 3]), 2000);
+        assert_eq!(matrix.group(&[3, 1]), 2000);
+        assert_eq!(matrix.group(&[0, 4]), FORBIDDEN);
+        for group in [[0, 1, 2], [4, 2, 0], [3, 1, 4]] {
+            let by_id: Vec<usize> = group.iter().map(|&n| ids[n]).collect();
+            assert_eq!(matrix.group(&group), costs.group(&by_id));
+        }
+        // only half of it is stored, but every pair reads the same both ways
+        for i in 0..ids.len() {
+            assert_eq!(matrix.pair(i, i), 0);
+            for j in 0..ids.len() {
+                assert_eq!(matrix.pair(i, j), matrix.pair(j, i));
+            }
+        }
+        assert_eq!(matrix.joining(2, &[0, 2, 4]), 2000);
+    }
+}
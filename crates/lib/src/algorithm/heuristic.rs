@@ -0,0 +1,138 @@
+use super::cost::{CostMatrix, Costs};
+use super::progress::Watch;
+use tracing::debug;
+
+/// Groups people quickly enough for thousands of them. A greedy start puts everyone with whoever
+/// is cheapest to add to their group, and then people in different groups swap places for as
+/// long as any swap makes things better. For pairs, that's the classic 2-opt: `(a b)(c d)`
+/// becomes `(a c)(b d)` or `(a d)(b c)`.
+///
+/// Each round of swaps goes over every two people, so it's O(n²), and it usually takes a handful
+/// of rounds. The result is as good as it gets without a whole group of people moving at once,
+/// which is close to the best, but not always the best. Stops early when the time is up or it
+/// got cancelled.
+pub(crate) fn solve_heuristic(
+    ids: &[usize],
+    sizes: &[usize],
+    costs: &Costs,
+    watch: &Watch<()>,
+) -> Vec<Vec<usize>> {
+    let matrix = CostMatrix::new(ids, costs);
+    let mut groups = greedy(&matrix, ids.len(), sizes);
+    improve(&matrix, &mut groups, watch);
+    groups
+        .into_iter()
+        .map(|group| group.into_iter().map(|n| ids[n]).collect())
+        .collect()
+}
+
+/// Fills the groups one after the other. Each starts with the first person left, and then takes
+/// whoever adds the least to it, until it's full.
+fn greedy(matrix: &CostMatrix, people: usize, sizes: &[usize]) -> Vec<Vec<usize>> {
+    let mut left: Vec<usize> = (0..people).collect();
+    let mut groups = Vec::with_capacity(sizes.len());
+    for &size in sizes {
+        let mut group = vec![left.remove(0)];
+        while group.len() < size && !left.is_empty() {
+            let n = left
+                .iter()
+                .enumerate()
+                .min_by_key(|&(_, &i)| matrix.joining(i, &group))
+                .map_or(0, |(n, _)| n);
+            group.push(left.remove(n));
+        }
+        groups.push(group);
+    }
+    groups
+}
+
+/// Swaps people between groups while that lowers the total cost. Returns how many rounds of
+/// swaps it took.
+fn improve(matrix: &CostMatrix, groups: &mut [Vec<usize>], watch: &Watch<()>) -> usize {
+    let mut rounds = 0;
+    loop {
+        let mut improved = false;
+        for g1 in 0..groups.len() {
+            if watch.should_stop() {
+                return rounds;
+            }
+            for g2 in g1 + 1..groups.len() {
+                for x in 0..groups[g1].len() {
+                    for y in 0..groups[g2].len() {
+                        let (a, b) = (groups[g1][x], groups[g2][y]);
+                        // a leaves g1 for g2 and b the other way round, minus meeting each other
+                        let before =
+                            matrix.joining(a, &groups[g1]) + matrix.joining(b, &groups[g2]);
+                        let after = matrix.joining(b, &groups[g1]) + matrix.joining(a, &groups[g2])
+                            - 2 * matrix.pair(a, b);
+                        if after < before {
+                            groups[g1][x] = b;
+                            groups[g2][y] = a;
+                            improved = true;
+                        }
+                    }
+                }
+            }
+        }
+        rounds += 1;
+        let score = groups.iter().map(|g| matrix.group(g)).sum();
+        debug!("Heuristic round {rounds} scored {score}");
+        watch.report(rounds, Some(score));
+        if !improved {
+            return rounds;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::History;
+    use crate::PairingConfig;
+    use crate::People;
+    use crate::algorithm::history::merge;
+
+    fn costs(pairs: &[&[(usize, usize)]]) -> Costs {
+        let mut history = History::default();
+        for round in pairs {
+            merge(&mut history, round);
+        }
+        Costs::new(&People::default(), &history, &PairingConfig::new())
+    }
+
+    #[test]
+    fn test_greedy() {
+        let costs = costs(&[&[(1, 2), (3, 4)], &[(1, 3)]]);
+        let ids = [1, 2, 3, 4];
+        let matrix = CostMatrix::new(&ids, &costs);
+        // 1 goes with 4, the only one it hasn't met, which leaves 2 and 3
+        assert_eq!(greedy(&matrix, 4, &[2, 2]), vec![vec![0, 3], vec![1, 2]]);
+        assert_eq!(greedy(&matrix, 4, &[3, 1]), vec![vec![0, 3, 1], vec![2]]);
+    }
+
+    #[test]
+    fn test_swaps_fix_greedy() {
+        // greedy pairs 1 with 2 for free, which leaves 3 and 4 with their two repeats, when
+        // (1 4)(2 3) has none at all
+        let costs = costs(&[&[(3, 4)], &[(3, 4)], &[(1, 3)], &[(2, 4)]]);
+        let ids = [1, 2, 3, 4];
+        let matrix = CostMatrix::new(&ids, &costs);
+        let mut groups = greedy(&matrix, 4, &[2, 2]);
+        assert_eq!(groups, vec![vec![0, 1], vec![2, 3]]);
+        let rounds = improve(&matrix, &mut groups, &Watch::new(&PairingConfig::new()));
+        assert_eq!(rounds, 2);
+        let score: isize = groups.iter().map(|g| matrix.group(g)).sum();
+        assert_eq!(score, 0);
+        assert_eq!(groups, vec![vec![2, 1], vec![0, 3]]);
+    }
+
+    #[test]
+    fn test_stops_when_cancelled() {
+        let costs = costs(&[&[(3, 4)], &[(3, 4)]]);
+        let ids = [1, 2, 3, 4];
+        let config = PairingConfig::new().with_time_limit(std::time::Duration::ZERO);
+        let groups = solve_heuristic(&ids, &[2, 2], &costs, &Watch::new(&config));
+        // no time for swaps, so whatever the greedy start came up with
+        assert_eq!(groups, vec![vec![1, 2], vec![3, 4]]);
+    }
+}
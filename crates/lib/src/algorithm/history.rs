@@ -1,19 +1,43 @@
 use crate::BuddyError;
+use crate::Groups;
 use crate::Person;
+use chrono::NaiveDateTime;
 use glob::glob;
 use serde::Deserialize;
 use serde::Serialize;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use tracing::debug;
 
+/// The format of history file names, which doubles as the time the round happened.
+pub(crate) const FILE_NAME_FORMAT: &str = "%Y%m%d_%H%M%S";
+
 /// Contains history of past pairings
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct History {
     map: HashMap<(usize, usize), usize>,
+    /// Every meeting of each pair, oldest first. Keyed with the smaller ID first.
+    #[serde(skip)]
+    meetings: HashMap<(usize, usize), Vec<Meeting>>,
+    /// How many rounds went into this history.
+    #[serde(skip)]
+    rounds: usize,
+    /// How many times each person sat a round out.
+    #[serde(skip)]
+    byes: HashMap<usize, usize>,
     #[serde(skip)]
     stats: HistoryStats,
 }
 
+/// One time a pair met.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Meeting {
+    /// Which round it happened in, counting from 0 for the oldest round in the [`History`].
+    pub round: usize,
+    /// When the round happened, if we could tell from the history file name.
+    pub at: Option<NaiveDateTime>,
+}
+
 impl Default for History {
     fn default() -> Self {
         Self::new()
@@ -24,20 +48,37 @@ impl History {
     /// Usually the history is saved in some directory. Give it a directory name to read that
     /// history. If you're just starting out, give it the desired directory. If it doesn't exist,
     /// it will be created.
+    ///
+    /// Files are read in order of their names, which are timestamps, so oldest first.
     pub fn from_dir(dir: &str) -> Result<Self, BuddyError> {
+        Self::from_files(&history_files(dir)?)
+    }
+
+    /// Like [`from_dir`](Self::from_dir), but without the newest round. That's the history the
+    /// newest round was paired against, to [`reroll`](crate::reroll) it with.
+    pub fn before_last(dir: &str) -> Result<Self, BuddyError> {
+        let mut paths = history_files(dir)?;
+        paths.pop();
+        Self::from_files(&paths)
+    }
+
+    fn from_files(paths: &[PathBuf]) -> Result<Self, BuddyError> {
         let mut history = Self::new();
 
-        // read pairs from dir of files
-        let pattern = format!("{dir}/*.json");
-        for path in glob(&pattern)? {
+        for path in paths {
             debug!("Reading history file {path:?}");
-            let pairs = std::fs::read_to_string(path?)?;
-            let pairs: Vec<(Person, Person)> = serde_json::from_str(&pairs)?;
-            let pairs = pairs.iter().map(|p| (p.0.id, p.1.id)).collect();
+            let at = timestamp_from_path(path);
+            let groups = read_round(path)?.inner();
+            let pairs: Vec<_> = groups.iter().flat_map(|g| pairs_in_group(g)).collect();
             history.stats.files_read += 1;
-            merge(&mut history, &pairs);
+            merge_at(&mut history, &pairs, at);
+            // anyone in a group of their own sat out too, not just those paired with the evenizer
+            for group in groups.iter().filter(|g| g.len() == 1) {
+                *history.byes.entry(group[0].id).or_default() += 1;
+            }
         }
         history.stats.pairs = history.len();
+        history.stats.byes = history.byes.clone();
         Ok(history)
     }
 
@@ -50,12 +91,45 @@ impl History {
         let scores = HashMap::new();
         Self {
             map: scores,
+            meetings: HashMap::new(),
+            rounds: 0,
+            byes: HashMap::new(),
             stats: HistoryStats::default(),
         }
     }
 
-    pub fn stats(&self) -> HistoryStats {
-        self.stats
+    /// How many rounds went into this history.
+    pub fn rounds(&self) -> usize {
+        self.rounds
+    }
+
+    /// Every time the two people met, oldest first, in either order.
+    pub fn meetings(&self, pair: (usize, usize)) -> &[Meeting] {
+        let key = (pair.0.min(pair.1), pair.0.max(pair.1));
+        self.meetings.get(&key).map_or(&[], Vec::as_slice)
+    }
+
+    /// When the newest meeting with a timestamp happened, if any has one.
+    pub fn latest(&self) -> Option<NaiveDateTime> {
+        self.meetings.values().flatten().filter_map(|m| m.at).max()
+    }
+
+    /// Every pair that met in the last `rounds` rounds, smaller ID first.
+    pub fn met_within(&self, rounds: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let start = self.rounds.saturating_sub(rounds);
+        self.meetings
+            .iter()
+            .filter(move |(_, meetings)| meetings.iter().any(|m| m.round >= start))
+            .map(|(pair, _)| *pair)
+    }
+
+    /// How many times the person sat a round out, because there was nobody left to pair them with.
+    pub fn byes(&self, id: usize) -> usize {
+        self.byes.get(&id).copied().unwrap_or(0)
+    }
+
+    pub fn stats(&self) -> &HistoryStats {
+        &self.stats
     }
 
     fn insert(&mut self, pair: (usize, usize), iteration: usize) {
@@ -96,8 +170,83 @@ impl History {
     }
 }
 
-fn merge(history: &mut History, pairs: &Vec<(usize, usize)>) {
+/// One saved round, as found in the history directory.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HistoryFile {
+    /// Older files are just the list of groups. Pairs used to be saved as two-element arrays,
+    /// which read fine as groups of two.
+    Legacy(Vec<Vec<Person>>),
+    Round(Groups),
+}
+
+/// The newest round in the history directory, along with the file it's in. `None` if nothing
+/// was saved yet.
+pub fn last_round(dir: &str) -> Result<Option<(PathBuf, Groups)>, BuddyError> {
+    let Some(path) = history_files(dir)?.pop() else {
+        return Ok(None);
+    };
+    let groups = read_round(&path)?;
+    Ok(Some((path, groups)))
+}
+
+/// All history files in the directory, oldest first.
+fn history_files(dir: &str) -> Result<Vec<PathBuf>, BuddyError> {
+    let pattern = format!("{dir}/*.json");
+    let mut paths = Vec::new();
+    for path in glob(&pattern)? {
+        paths.push(path?);
+    }
+    Ok(paths)
+}
+
+fn read_round(path: &Path) -> Result<Groups, BuddyError> {
+    let file = std::fs::read_to_string(path)?;
+    Ok(match serde_json::from_str(&file)? {
+        HistoryFile::Legacy(groups) => Groups::new(groups),
+        HistoryFile::Round(round) => round,
+    })
+}
+
+/// All the pairs of people within a group, since everyone in a group meets everyone else.
+pub(crate) fn pairs_in_group(group: &[Person]) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for (n, p1) in group.iter().enumerate() {
+        for p2 in &group[n + 1..] {
+            pairs.push((p1.id, p2.id));
+        }
+    }
+    pairs
+}
+
+/// The time a round happened, going by the name of its history file.
+fn timestamp_from_path(path: &Path) -> Option<NaiveDateTime> {
+    let stem = path.file_stem()?.to_str()?;
+    NaiveDateTime::parse_from_str(stem, FILE_NAME_FORMAT).ok()
+}
+
+/// Adds a round of pairs to the history, without knowing when it happened.
+#[cfg(test)]
+pub(crate) fn merge(history: &mut History, pairs: &[(usize, usize)]) {
+    merge_at(history, pairs, None);
+}
+
+/// Adds a round of pairs to the history that happened at the given time. Whoever is paired with
+/// the evenizer gets a bye.
+pub(crate) fn merge_at(history: &mut History, pairs: &[(usize, usize)], at: Option<NaiveDateTime>) {
+    let round = history.rounds;
+    history.rounds += 1;
     for p in pairs {
+        match *p {
+            (id, usize::MAX) | (usize::MAX, id) => *history.byes.entry(id).or_default() += 1,
+            _ => {}
+        }
+        let key = (p.0.min(p.1), p.0.max(p.1));
+        history
+            .meetings
+            .entry(key)
+            .or_default()
+            .push(Meeting { round, at });
         if history.contains(p) {
             let it = history.get(*p).unwrap();
 
@@ -109,13 +258,16 @@ fn merge(history: &mut History, pairs: &Vec<(usize, usize)>) {
 }
 
 /// Saves some stats about the [`History`].
-#[derive(Debug, Copy, Clone, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct HistoryStats {
     /// How many files of history were read
     pub files_read: usize,
 
     /// How many existing [`Pairs`][crate::Pairs] are in the history.
     pub pairs: usize,
+
+    /// How many times each person sat a round out, by ID. People who never did aren't in here.
+    pub byes: HashMap<usize, usize>,
 }
 
 #[cfg(test)]
@@ -190,6 +342,79 @@ mod test {
         assert_eq!(h.len(), 1);
     }
 
+    #[test]
+    fn test_pairs_in_group() {
+        let group = vec![
+            Person::new(1, "A".to_string()),
+            Person::new(2, "B".to_string()),
+            Person::new(3, "C".to_string()),
+        ];
+        assert_eq!(pairs_in_group(&group), vec![(1, 2), (1, 3), (2, 3)]);
+        assert!(pairs_in_group(&group[..1]).is_empty());
+    }
+
+    #[test]
+    fn test_history_file_formats() {
+        let legacy = r#"[[{"id": 1, "name": "A"}, {"id": 2, "name": "B"}]]"#;
+        let round = r#"{"groups": [[{"id": 1, "name": "A"}, {"id": 2, "name": "B"}]], "seed": 3}"#;
+        assert!(matches!(
+            serde_json::from_str(legacy).unwrap(),
+            HistoryFile::Legacy(_)
+        ));
+        match serde_json::from_str(round).unwrap() {
+            HistoryFile::Round(groups) => assert_eq!(groups.seed(), Some(3)),
+            HistoryFile::Legacy(_) => panic!("expected a round"),
+        }
+    }
+
+    #[test]
+    fn test_meetings() {
+        let mut h = History::new();
+        let at = NaiveDateTime::parse_from_str("20250101_120000", FILE_NAME_FORMAT).ok();
+        merge_at(&mut h, &[(1, 2)], at);
+        merge(&mut h, &[(3, 4)]);
+        merge(&mut h, &[(2, 1)]);
+        assert_eq!(h.rounds(), 3);
+        assert_eq!(
+            h.meetings((2, 1)),
+            &[Meeting { round: 0, at }, Meeting { round: 2, at: None }]
+        );
+        assert!(h.meetings((1, 3)).is_empty());
+    }
+
+    #[test]
+    fn test_byes() {
+        let mut h = History::new();
+        merge(&mut h, &[(1, 2), (3, usize::MAX)]);
+        merge(&mut h, &[(1, 3), (usize::MAX, 2)]);
+        merge(&mut h, &[(2, 3), (1, usize::MAX)]);
+        merge(&mut h, &[(1, 2), (3, usize::MAX)]);
+        assert_eq!(h.byes(1), 1);
+        assert_eq!(h.byes(2), 1);
+        assert_eq!(h.byes(3), 2);
+        assert_eq!(h.byes(4), 0);
+    }
+
+    #[test]
+    fn test_met_within() {
+        let mut h = History::new();
+        merge(&mut h, &[(2, 1)]);
+        merge(&mut h, &[(3, 4)]);
+        merge(&mut h, &[(1, 3)]);
+        let mut recent: Vec<_> = h.met_within(2).collect();
+        recent.sort_unstable();
+        assert_eq!(recent, vec![(1, 3), (3, 4)]);
+        assert_eq!(h.met_within(5).count(), 3);
+        assert_eq!(h.met_within(0).count(), 0);
+    }
+
+    #[test]
+    fn test_timestamp_from_path() {
+        let at = timestamp_from_path(Path::new("history/20250213_205644.json")).unwrap();
+        assert_eq!(at.to_string(), "2025-02-13 20:56:44");
+        assert_eq!(timestamp_from_path(Path::new("history/edited.json")), None);
+    }
+
     #[test]
     fn test_get_either_order() {
         let mut h = History::new();
@@ -0,0 +1,343 @@
+use super::cost::{Costs, FORBIDDEN, SCALE};
+use super::history::merge_at;
+use super::mentoring::solve_mentoring;
+use super::progress::Watch;
+use super::{
+    Groups, PairingConfig, Pairs, check_roster, check_working_hours, pinned_groups, random_seed,
+    solve_exact, split, to_people,
+};
+use crate::BuddyError;
+use crate::History;
+use crate::People;
+use genetic_algorithm::strategy::evolve::prelude::*;
+use std::collections::HashMap;
+use tracing::{debug, trace};
+
+/// How often we go over all rounds trying to improve them before settling.
+const MAX_PASSES: usize = 20;
+
+/// Plans the next `rounds` rounds of [`Pairs`] together, so that the whole stretch has as few
+/// repeats as possible, counting both the [`History`] and the planned rounds themselves.
+///
+/// Pairing one round at a time can paint us into a corner, where the pairs left for later rounds
+/// have all met already. So after a first, greedy plan, every round gets re-solved in turn
+/// against the history plus all the other planned rounds, and kept if that doesn't make the plan
+/// worse. That still gets stuck sometimes, so if there are repeats left a genetic algorithm
+/// shuffles all rounds at once, and wins if it finds a better plan. It uses the seed from the
+/// [`PairingConfig`], if there is one.
+///
+/// Each round is solved exactly like [`Solver::Exact`](super::Solver::Exact) does, so this only
+/// works for pairs, but respects the exclusions, decay, diversity, scorers and mentoring from the
+/// [`PairingConfig`]. Mentoring plans skip the genetic algorithm. The cooldown only looks at the
+/// history, since the plan avoids repeats anyway. Pins only apply to the first round, and odd
+/// rosters always take turns sitting out, whatever the
+/// [`OddPolicy`](super::OddPolicy).
+///
+/// The time limit covers the whole search, and the plan is as good as it got by then.
+pub fn plan_ahead(
+    people: People,
+    last: &History,
+    config: &PairingConfig,
+    rounds: usize,
+) -> Result<Vec<Pairs>, BuddyError> {
+    if config.group_size != 2 {
+        return Err(BuddyError::ExactSolverNeedsPairs(config.group_size));
+    }
+    let people = people.without(&config.absent)?;
+    check_roster(&people)?;
+    check_working_hours(&people, config)?;
+    let planner = Planner::new(&people, last, config)?;
+
+    // a greedy start, one round after the other
+    let mut plan = Vec::with_capacity(rounds);
+    for _ in 0..rounds {
+        let round = planner.solve(plan.len(), &plan)?;
+        plan.push(round);
+    }
+
+    let watch = Watch::new(config);
+    let mut best = (planner.total(&plan), plan.clone());
+    for pass in 0..MAX_PASSES {
+        if watch.should_stop() {
+            break;
+        }
+        let mut changed = false;
+        for r in 0..plan.len() {
+            let costs = planner.costs(&plan, r);
+            let candidate = planner.solve(r, &plan)?;
+            if candidate != plan[r]
+                && round_cost(&costs, &candidate) <= round_cost(&costs, &plan[r])
+            {
+                plan[r] = candidate;
+                changed = true;
+            }
+        }
+        let total = planner.total(&plan);
+        debug!("Lookahead pass {pass} scored {total}");
+        if total < best.0 {
+            best = (total, plan.clone());
+        }
+        if !changed || total == 0 {
+            break;
+        }
+    }
+    let mut plan = best.1;
+    if best.0 > 0 && !config.mentoring && !plan.is_empty() && !watch.should_stop() {
+        config.check_genetic()?;
+        let seed = config.seed.unwrap_or_else(random_seed);
+        if let Some(evolved) = planner.evolve(&plan, seed, &watch)? {
+            plan = evolved;
+        }
+    }
+    if watch.is_cancelled() {
+        return Err(BuddyError::Cancelled);
+    }
+
+    plan.iter()
+        .map(|round| Ok(Groups::new(to_people(&people, round)?)))
+        .collect()
+}
+
+struct Planner<'a> {
+    people: &'a People,
+    last: &'a History,
+    config: &'a PairingConfig,
+    ids: Vec<usize>,
+    pinned: Vec<Vec<usize>>,
+}
+
+impl<'a> Planner<'a> {
+    fn new(
+        people: &'a People,
+        last: &'a History,
+        config: &'a PairingConfig,
+    ) -> Result<Self, BuddyError> {
+        let costs = Costs::new(people, last, config);
+        let pinned = pinned_groups(people, &costs, config)?;
+        let mut ids = people.as_ids();
+        if config.mentoring {
+            ids.retain(|id| *id != usize::MAX);
+        }
+        Ok(Self {
+            people,
+            last,
+            config,
+            ids,
+            pinned,
+        })
+    }
+
+    /// The costs for round `r`, as if every other planned round already happened.
+    fn costs(&self, plan: &[Vec<Vec<usize>>], r: usize) -> Costs {
+        let mut history = self.last.clone();
+        for (n, round) in plan.iter().enumerate() {
+            if n != r {
+                let pairs: Vec<(usize, usize)> = round
+                    .iter()
+                    .filter(|g| g.len() == 2)
+                    .map(|g| (g[0], g[1]))
+                    .collect();
+                merge_at(&mut history, &pairs, None);
+            }
+        }
+        // the made-up rounds would push the real ones out of the cooldown
+        Costs::new(self.people, &history, self.config).with_cooldown_from(self.last)
+    }
+
+    /// The cost of the whole plan, each round counting the history and the rounds before it.
+    fn total(&self, plan: &[Vec<Vec<usize>>]) -> isize {
+        (0..plan.len())
+            .map(|r| round_cost(&self.costs(&plan[..r], usize::MAX), &plan[r]))
+            .sum()
+    }
+
+    /// The best round `r` given all other rounds in the plan.
+    fn solve(&self, r: usize, plan: &[Vec<Vec<usize>>]) -> Result<Vec<Vec<usize>>, BuddyError> {
+        let costs = self.costs(plan, r);
+        let (mut groups, ids) = if r == 0 {
+            let ids: Vec<usize> = self
+                .ids
+                .iter()
+                .copied()
+                .filter(|id| !self.pinned.iter().flatten().any(|p| p == id))
+                .collect();
+            (self.pinned.clone(), ids)
+        } else {
+            (Vec::new(), self.ids.clone())
+        };
+        if ids.is_empty() {
+            return Ok(groups);
+        }
+        let mut solved = if self.config.mentoring {
+            solve_mentoring(self.people, &ids, &costs)?
+        } else {
+            solve_exact(&ids, &costs)?
+        };
+        groups.append(&mut solved);
+        Ok(groups)
+    }
+}
+
+impl Planner<'_> {
+    /// Runs a genetic algorithm over all rounds at once, and returns its plan if it beats `plan`.
+    fn evolve(
+        &self,
+        plan: &[Vec<Vec<usize>>],
+        seed: u64,
+        watch: &Watch<MultiUniqueGenotype<usize>>,
+    ) -> Result<Option<Vec<Vec<Vec<usize>>>>, BuddyError> {
+        // the pins stay where they are, everyone else gets shuffled
+        let pinned = self.pinned.len();
+        let lists: Vec<Vec<usize>> = plan
+            .iter()
+            .enumerate()
+            .map(|(r, round)| {
+                let skip = if r == 0 { pinned } else { 0 };
+                round.iter().skip(skip).flatten().copied().collect()
+            })
+            .collect();
+        let fitness = PlanFitness {
+            costs: Costs::new(self.people, self.last, self.config),
+            sizes: lists.iter().map(Vec::len).collect(),
+        };
+        let current = fitness.score(&lists.concat());
+
+        let genotype = MultiUniqueGenotype::builder()
+            .with_allele_lists(lists)
+            .build()
+            .map_err(|e| BuddyError::SolverFailure(e.0.to_string()))?;
+        let mut evolve = Evolve::builder()
+            .with_genotype(genotype)
+            .with_target_population_size(self.config.population_size)
+            .with_max_stale_generations(self.config.max_stale_generations)
+            .with_fitness(fitness.clone())
+            .with_fitness_ordering(FitnessOrdering::Minimize)
+            .with_target_fitness_score(0)
+            .with_replace_on_equal_fitness(true)
+            .with_mutate(MutateSingleGene::new(self.config.mutation_rate))
+            .with_crossover(CrossoverSinglePoint::new(self.config.crossover_rate, 0.5))
+            .with_select(SelectElite::new(
+                self.config.replacement_rate,
+                self.config.elitism_rate,
+            ))
+            .with_extension(watch.clone())
+            .with_rng_seed_from_u64(seed)
+            .build()
+            .map_err(|e| BuddyError::SolverFailure(e.0.to_string()))?;
+        evolve.call();
+        let genes = evolve
+            .best_genes()
+            .ok_or_else(|| BuddyError::SolverFailure("it found no plan at all".to_string()))?;
+        let evolved = fitness.score(&genes);
+        debug!("Lookahead evolved a plan scoring {evolved}, up from {current}");
+        if evolved >= current {
+            return Ok(None);
+        }
+
+        let mut rounds = fitness.rounds(&genes);
+        let mut first = self.pinned.clone();
+        first.append(&mut rounds[0]);
+        rounds[0] = first;
+        Ok(Some(rounds))
+    }
+}
+
+/// Scores a whole plan of pairs, laid out round after round. Besides the usual costs, every pair
+/// costs as much as a meeting for each time it was already planned in an earlier round.
+#[derive(Clone, Debug)]
+struct PlanFitness {
+    costs: Costs,
+    sizes: Vec<usize>,
+}
+
+impl PlanFitness {
+    /// Cuts the genes back into rounds of pairs.
+    fn rounds(&self, genes: &[usize]) -> Vec<Vec<Vec<usize>>> {
+        split(genes, &self.sizes)
+            .map(|round| round.chunks(2).map(<[usize]>::to_vec).collect())
+            .collect()
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn score(&self, genes: &[usize]) -> isize {
+        let mut planned: HashMap<(usize, usize), isize> = HashMap::new();
+        let mut score = 0;
+        for pair in split(genes, &self.sizes).flat_map(|round| round.chunks(2)) {
+            let (i, j) = (pair[0].min(pair[1]), pair[0].max(pair[1]));
+            let before = planned.entry((i, j)).or_default();
+            score += self.costs.pair(i, j).unwrap_or(FORBIDDEN) + *before * SCALE as isize;
+            *before += 1;
+        }
+        score
+    }
+}
+
+impl Fitness for PlanFitness {
+    type Genotype = MultiUniqueGenotype<usize>;
+    fn calculate_for_chromosome(
+        &mut self,
+        chromosome: &FitnessChromosome<Self>,
+        _genotype: &FitnessGenotype<Self>,
+    ) -> Option<FitnessValue> {
+        let score = self.score(chromosome.genes());
+        trace!("Score for plan {:?}: {score}", chromosome.genes());
+        Some(score)
+    }
+}
+
+fn round_cost(costs: &Costs, round: &[Vec<usize>]) -> isize {
+    round.iter().map(|g| costs.group(g)).sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Exclusions;
+    use crate::algorithm::test_util::{ids, people};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_no_repeats_across_rounds() {
+        for n in [6, 8, 10] {
+            let plan =
+                plan_ahead(people(n), &History::default(), &PairingConfig::new(), n - 1).unwrap();
+            assert_eq!(plan.len(), n - 1);
+            let mut seen = HashSet::new();
+            for round in &plan {
+                for pair in ids(round) {
+                    assert!(seen.insert(pair), "{pair:?} repeats for {n} people");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_avoids_history() {
+        let mut history = History::default();
+        merge_at(&mut history, &[(1, 2), (3, 4), (5, 6)], None);
+        let plan = plan_ahead(people(6), &history, &PairingConfig::new(), 4).unwrap();
+        for round in &plan {
+            for pair in ids(round) {
+                assert_eq!(history.get(pair), None);
+            }
+        }
+    }
+
+    #[test]
+    fn test_respects_exclusions() {
+        let mut exclusions = Exclusions::new();
+        exclusions.insert(1, 2);
+        let config = PairingConfig::new().with_exclusions(exclusions);
+        let plan = plan_ahead(people(4), &History::default(), &config, 3).unwrap();
+        for round in &plan {
+            assert!(!ids(round).contains(&(1, 2)));
+        }
+    }
+
+    #[test]
+    fn test_needs_pairs() {
+        let config = PairingConfig::new().with_group_size(3);
+        let r = plan_ahead(people(6), &History::default(), &config, 2);
+        assert!(matches!(r, Err(BuddyError::ExactSolverNeedsPairs(3))));
+    }
+}
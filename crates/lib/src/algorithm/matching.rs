@@ -0,0 +1,740 @@
+//! Maximum weight matching in general graphs.
+//!
+//! This is Edmonds' blossom algorithm with dual variables, following Joris van Rantwijk's
+//! well known `mwmatching.py` implementation, and runs in O(n³). We only ever use integer
+//! weights, which keeps all the dual variable arithmetic exact.
+
+/// Marker for "no vertex", "no edge" or "no blossom".
+const NONE: usize = usize::MAX;
+
+/// Computes a maximum weight matching for the undirected graph given as `(i, j, weight)` edges.
+///
+/// If `max_cardinality` is set, only matchings with the maximum number of edges are considered,
+/// and of those the one with the biggest weight is returned.
+///
+/// Returns a list with one entry per vertex, holding the vertex it's matched to, if any.
+pub(crate) fn max_weight_matching(
+    edges: &[(usize, usize, i64)],
+    max_cardinality: bool,
+) -> Vec<Option<usize>> {
+    if edges.is_empty() {
+        return Vec::new();
+    }
+    let mut m = Matcher::new(edges, max_cardinality);
+    m.solve();
+    m.mate
+        .iter()
+        .map(|&p| if p == NONE { None } else { Some(m.endpoint[p]) })
+        .collect()
+}
+
+struct Matcher<'a> {
+    edges: &'a [(usize, usize, i64)],
+    max_cardinality: bool,
+    nvertex: usize,
+    /// `endpoint[p]` is the vertex at endpoint `p`; edge `k` has endpoints `2k` and `2k + 1`.
+    endpoint: Vec<usize>,
+    /// For each vertex, the remote endpoints of its incident edges.
+    neighbend: Vec<Vec<usize>>,
+    /// For each vertex, the remote endpoint of its matched edge.
+    mate: Vec<usize>,
+    /// 0 = free, 1 = S, 2 = T, 5 = S with breadcrumb, per vertex and top-level blossom.
+    label: Vec<u8>,
+    labelend: Vec<usize>,
+    inblossom: Vec<usize>,
+    blossomparent: Vec<usize>,
+    blossomchilds: Vec<Vec<usize>>,
+    blossombase: Vec<usize>,
+    blossomendps: Vec<Vec<usize>>,
+    bestedge: Vec<usize>,
+    blossombestedges: Vec<Option<Vec<usize>>>,
+    unusedblossoms: Vec<usize>,
+    dualvar: Vec<i64>,
+    allowedge: Vec<bool>,
+    queue: Vec<usize>,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(edges: &'a [(usize, usize, i64)], max_cardinality: bool) -> Self {
+        let nvertex = edges
+            .iter()
+            .map(|&(i, j, _)| i.max(j) + 1)
+            .max()
+            .unwrap_or(0);
+        let maxweight = edges.iter().map(|e| e.2).max().unwrap_or(0).max(0);
+        let endpoint = (0..2 * edges.len())
+            .map(|p| {
+                let (i, j, _) = edges[p / 2];
+                if p % 2 == 0 { i } else { j }
+            })
+            .collect();
+        let mut neighbend = vec![Vec::new(); nvertex];
+        for (k, &(i, j, _)) in edges.iter().enumerate() {
+            neighbend[i].push(2 * k + 1);
+            neighbend[j].push(2 * k);
+        }
+        let mut dualvar = vec![maxweight; nvertex];
+        dualvar.extend(vec![0; nvertex]);
+        Self {
+            edges,
+            max_cardinality,
+            nvertex,
+            endpoint,
+            neighbend,
+            mate: vec![NONE; nvertex],
+            label: vec![0; 2 * nvertex],
+            labelend: vec![NONE; 2 * nvertex],
+            inblossom: (0..nvertex).collect(),
+            blossomparent: vec![NONE; 2 * nvertex],
+            blossomchilds: vec![Vec::new(); 2 * nvertex],
+            blossombase: (0..nvertex).chain(vec![NONE; nvertex]).collect(),
+            blossomendps: vec![Vec::new(); 2 * nvertex],
+            bestedge: vec![NONE; 2 * nvertex],
+            blossombestedges: vec![None; 2 * nvertex],
+            unusedblossoms: (nvertex..2 * nvertex).collect(),
+            dualvar,
+            allowedge: vec![false; edges.len()],
+            queue: Vec::new(),
+        }
+    }
+
+    /// Twice the slack of edge `k`; zero for edges that are tight.
+    fn slack(&self, k: usize) -> i64 {
+        let (i, j, wt) = self.edges[k];
+        self.dualvar[i] + self.dualvar[j] - 2 * wt
+    }
+
+    /// All the vertices contained in blossom `b`.
+    fn blossom_leaves(&self, b: usize) -> Vec<usize> {
+        if b < self.nvertex {
+            return vec![b];
+        }
+        let mut leaves = Vec::new();
+        for &t in &self.blossomchilds[b] {
+            leaves.extend(self.blossom_leaves(t));
+        }
+        leaves
+    }
+
+    /// Index into the children of blossom `b`, wrapping around like Python's negative indices.
+    fn wrap(&self, b: usize, j: isize) -> usize {
+        j.rem_euclid(self.blossomchilds[b].len() as isize) as usize
+    }
+
+    /// Assigns label `t` to the top-level blossom containing vertex `w`, coming through
+    /// endpoint `p`.
+    fn assign_label(&mut self, w: usize, t: u8, p: usize) {
+        let b = self.inblossom[w];
+        self.label[w] = t;
+        self.label[b] = t;
+        self.labelend[w] = p;
+        self.labelend[b] = p;
+        self.bestedge[w] = NONE;
+        self.bestedge[b] = NONE;
+        if t == 1 {
+            // b became an S-blossom, add its vertices to the queue
+            let leaves = self.blossom_leaves(b);
+            self.queue.extend(leaves);
+        } else if t == 2 {
+            // b became a T-blossom, assign label S to its mate
+            let base = self.blossombase[b];
+            let mate = self.mate[base];
+            self.assign_label(self.endpoint[mate], 1, mate ^ 1);
+        }
+    }
+
+    /// Traces back from `v` and `w` to find either a new blossom or an augmenting path.
+    /// Returns the base of the new blossom, or `NONE` for an augmenting path.
+    fn scan_blossom(&mut self, mut v: usize, mut w: usize) -> usize {
+        let mut path = Vec::new();
+        let mut base = NONE;
+        while v != NONE || w != NONE {
+            let mut b = self.inblossom[v];
+            if self.label[b] & 4 != 0 {
+                base = self.blossombase[b];
+                break;
+            }
+            path.push(b);
+            self.label[b] = 5;
+            if self.labelend[b] == NONE {
+                // the base of blossom b is single, stop tracing this path
+                v = NONE;
+            } else {
+                v = self.endpoint[self.labelend[b]];
+                b = self.inblossom[v];
+                // b is a T-blossom, trace one more step back
+                v = self.endpoint[self.labelend[b]];
+            }
+            // alternate between both paths
+            if w != NONE {
+                std::mem::swap(&mut v, &mut w);
+            }
+        }
+        for b in path {
+            self.label[b] = 1;
+        }
+        base
+    }
+
+    /// Constructs a new blossom with the given base, containing edge `k` which connects a pair
+    /// of S-vertices.
+    fn add_blossom(&mut self, base: usize, k: usize) {
+        let (mut v, mut w, _) = self.edges[k];
+        let bb = self.inblossom[base];
+        let mut bv = self.inblossom[v];
+        let mut bw = self.inblossom[w];
+        let b = self.unusedblossoms.pop().expect("ran out of blossoms");
+        self.blossombase[b] = base;
+        self.blossomparent[b] = NONE;
+        self.blossomparent[bb] = b;
+
+        let mut path = Vec::new();
+        let mut endps = Vec::new();
+        // trace back from v to base
+        while bv != bb {
+            self.blossomparent[bv] = b;
+            path.push(bv);
+            endps.push(self.labelend[bv]);
+            v = self.endpoint[self.labelend[bv]];
+            bv = self.inblossom[v];
+        }
+        path.push(bb);
+        path.reverse();
+        endps.reverse();
+        endps.push(2 * k);
+        // trace back from w to base
+        while bw != bb {
+            self.blossomparent[bw] = b;
+            path.push(bw);
+            endps.push(self.labelend[bw] ^ 1);
+            w = self.endpoint[self.labelend[bw]];
+            bw = self.inblossom[w];
+        }
+        self.blossomchilds[b] = path.clone();
+        self.blossomendps[b] = endps;
+
+        self.label[b] = 1;
+        self.labelend[b] = self.labelend[bb];
+        self.dualvar[b] = 0;
+        for v in self.blossom_leaves(b) {
+            if self.label[self.inblossom[v]] == 2 {
+                // this T-vertex turns into an S-vertex, so it needs scanning
+                self.queue.push(v);
+            }
+            self.inblossom[v] = b;
+        }
+
+        // compute the least-slack edges from the new blossom to other S-blossoms
+        let mut bestedgeto = vec![NONE; 2 * self.nvertex];
+        for &bv in &path {
+            let nblists: Vec<Vec<usize>> = match self.blossombestedges[bv].take() {
+                Some(list) => vec![list],
+                None => self
+                    .blossom_leaves(bv)
+                    .iter()
+                    .map(|&v| self.neighbend[v].iter().map(|p| p / 2).collect())
+                    .collect(),
+            };
+            for nblist in nblists {
+                for k in nblist {
+                    let (mut i, mut j, _) = self.edges[k];
+                    if self.inblossom[j] == b {
+                        std::mem::swap(&mut i, &mut j);
+                    }
+                    let bj = self.inblossom[j];
+                    if bj != b
+                        && self.label[bj] == 1
+                        && (bestedgeto[bj] == NONE || self.slack(k) < self.slack(bestedgeto[bj]))
+                    {
+                        bestedgeto[bj] = k;
+                    }
+                }
+            }
+            self.bestedge[bv] = NONE;
+        }
+        let best: Vec<usize> = bestedgeto.into_iter().filter(|&k| k != NONE).collect();
+        self.bestedge[b] = NONE;
+        for &k in &best {
+            if self.bestedge[b] == NONE || self.slack(k) < self.slack(self.bestedge[b]) {
+                self.bestedge[b] = k;
+            }
+        }
+        self.blossombestedges[b] = Some(best);
+    }
+
+    /// Expands blossom `b`, turning its children into top-level blossoms.
+    fn expand_blossom(&mut self, b: usize, endstage: bool) {
+        for s in self.blossomchilds[b].clone() {
+            self.blossomparent[s] = NONE;
+            if s < self.nvertex {
+                self.inblossom[s] = s;
+            } else if endstage && self.dualvar[s] == 0 {
+                self.expand_blossom(s, endstage);
+            } else {
+                for v in self.blossom_leaves(s) {
+                    self.inblossom[v] = s;
+                }
+            }
+        }
+
+        // if we expand a T-blossom during a stage, its children must be relabeled
+        if !endstage && self.label[b] == 2 {
+            let entrychild = self.inblossom[self.endpoint[self.labelend[b] ^ 1]];
+            let len = self.blossomchilds[b].len() as isize;
+            let mut j = self.blossomchilds[b]
+                .iter()
+                .position(|&c| c == entrychild)
+                .expect("entry child is part of the blossom") as isize;
+            let (jstep, endptrick) = if j & 1 == 1 {
+                // odd start index, go forward and wrap
+                j -= len;
+                (1, 0)
+            } else {
+                // even start index, go backward
+                (-1, 1)
+            };
+            let mut p = self.labelend[b];
+            while j != 0 {
+                // relabel the T-child
+                self.label[self.endpoint[p ^ 1]] = 0;
+                let e = self.blossomendps[b][self.wrap(b, j - endptrick)];
+                self.label[self.endpoint[e ^ endptrick as usize ^ 1]] = 0;
+                self.assign_label(self.endpoint[p ^ 1], 2, p);
+                // step to the next S-child and note its forward endpoint
+                self.allowedge[e / 2] = true;
+                j += jstep;
+                p = self.blossomendps[b][self.wrap(b, j - endptrick)] ^ endptrick as usize;
+                // step to the next T-child
+                self.allowedge[p / 2] = true;
+                j += jstep;
+            }
+            // relabel the base T-child without stepping through to its mate
+            let bv = self.blossomchilds[b][self.wrap(b, j)];
+            self.label[self.endpoint[p ^ 1]] = 2;
+            self.label[bv] = 2;
+            self.labelend[self.endpoint[p ^ 1]] = p;
+            self.labelend[bv] = p;
+            self.bestedge[bv] = NONE;
+            // continue along the blossom until we get back to the entry child
+            j += jstep;
+            while self.blossomchilds[b][self.wrap(b, j)] != entrychild {
+                let bv = self.blossomchilds[b][self.wrap(b, j)];
+                if self.label[bv] == 1 {
+                    // this child just got label S through one of its neighbours
+                    j += jstep;
+                    continue;
+                }
+                // if the child contains a reachable vertex, label it T
+                if let Some(v) = self
+                    .blossom_leaves(bv)
+                    .into_iter()
+                    .find(|&v| self.label[v] != 0)
+                {
+                    self.label[v] = 0;
+                    self.label[self.endpoint[self.mate[self.blossombase[bv]]]] = 0;
+                    self.assign_label(v, 2, self.labelend[v]);
+                }
+                j += jstep;
+            }
+        }
+
+        // recycle the blossom number
+        self.label[b] = 0;
+        self.labelend[b] = NONE;
+        self.blossomchilds[b] = Vec::new();
+        self.blossomendps[b] = Vec::new();
+        self.blossombase[b] = NONE;
+        self.blossombestedges[b] = None;
+        self.bestedge[b] = NONE;
+        self.unusedblossoms.push(b);
+    }
+
+    /// Swaps matched and unmatched edges along the path through blossom `b` from vertex `v` to
+    /// the base, making `v` the new base.
+    fn augment_blossom(&mut self, b: usize, v: usize) {
+        // bubble up from v to an immediate child of b
+        let mut t = v;
+        while self.blossomparent[t] != b {
+            t = self.blossomparent[t];
+        }
+        if t >= self.nvertex {
+            self.augment_blossom(t, v);
+        }
+        let len = self.blossomchilds[b].len() as isize;
+        let i = self.blossomchilds[b]
+            .iter()
+            .position(|&c| c == t)
+            .expect("child is part of the blossom");
+        let mut j = i as isize;
+        let (jstep, endptrick) = if i & 1 == 1 {
+            j -= len;
+            (1, 0)
+        } else {
+            (-1, 1)
+        };
+        while j != 0 {
+            j += jstep;
+            let t = self.blossomchilds[b][self.wrap(b, j)];
+            let p = self.blossomendps[b][self.wrap(b, j - endptrick)] ^ endptrick as usize;
+            if t >= self.nvertex {
+                self.augment_blossom(t, self.endpoint[p]);
+            }
+            j += jstep;
+            let t = self.blossomchilds[b][self.wrap(b, j)];
+            if t >= self.nvertex {
+                self.augment_blossom(t, self.endpoint[p ^ 1]);
+            }
+            // match the edge connecting those children
+            self.mate[self.endpoint[p]] = p ^ 1;
+            self.mate[self.endpoint[p ^ 1]] = p;
+        }
+        // rotate the children to put the new base at the front
+        self.blossomchilds[b].rotate_left(i);
+        self.blossomendps[b].rotate_left(i);
+        self.blossombase[b] = self.blossombase[self.blossomchilds[b][0]];
+    }
+
+    /// Swaps matched and unmatched edges along the augmenting path through edge `k`.
+    fn augment_matching(&mut self, k: usize) {
+        let (v, w, _) = self.edges[k];
+        for (mut s, mut p) in [(v, 2 * k + 1), (w, 2 * k)] {
+            loop {
+                let bs = self.inblossom[s];
+                if bs >= self.nvertex {
+                    self.augment_blossom(bs, s);
+                }
+                self.mate[s] = p;
+                if self.labelend[bs] == NONE {
+                    // reached a single vertex
+                    break;
+                }
+                let t = self.endpoint[self.labelend[bs]];
+                let bt = self.inblossom[t];
+                s = self.endpoint[self.labelend[bt]];
+                let j = self.endpoint[self.labelend[bt] ^ 1];
+                if bt >= self.nvertex {
+                    self.augment_blossom(bt, j);
+                }
+                self.mate[j] = self.labelend[bt];
+                p = self.labelend[bt] ^ 1;
+            }
+        }
+    }
+
+    fn solve(&mut self) {
+        let nvertex = self.nvertex;
+        // each iteration is a "stage", which either augments the matching or finishes
+        for _ in 0..nvertex {
+            self.label.fill(0);
+            self.bestedge.fill(NONE);
+            for b in nvertex..2 * nvertex {
+                self.blossombestedges[b] = None;
+            }
+            self.allowedge.fill(false);
+            self.queue.clear();
+
+            for v in 0..nvertex {
+                if self.mate[v] == NONE && self.label[self.inblossom[v]] == 0 {
+                    self.assign_label(v, 1, NONE);
+                }
+            }
+
+            let mut augmented = false;
+            loop {
+                while !augmented {
+                    let Some(v) = self.queue.pop() else { break };
+                    for p in self.neighbend[v].clone() {
+                        let k = p / 2;
+                        let w = self.endpoint[p];
+                        if self.inblossom[v] == self.inblossom[w] {
+                            // internal edge, ignore
+                            continue;
+                        }
+                        let mut kslack = 0;
+                        if !self.allowedge[k] {
+                            kslack = self.slack(k);
+                            if kslack <= 0 {
+                                self.allowedge[k] = true;
+                            }
+                        }
+                        if self.allowedge[k] {
+                            if self.label[self.inblossom[w]] == 0 {
+                                // w is free, label it T and its mate S
+                                self.assign_label(w, 2, p ^ 1);
+                            } else if self.label[self.inblossom[w]] == 1 {
+                                // found either a new blossom or an augmenting path
+                                let base = self.scan_blossom(v, w);
+                                if base == NONE {
+                                    self.augment_matching(k);
+                                    augmented = true;
+                                    break;
+                                }
+                                self.add_blossom(base, k);
+                            } else if self.label[w] == 0 {
+                                // w is inside a T-blossom but not yet reached from outside
+                                self.label[w] = 2;
+                                self.labelend[w] = p ^ 1;
+                            }
+                        } else if self.label[self.inblossom[w]] == 1 {
+                            let b = self.inblossom[v];
+                            if self.bestedge[b] == NONE || kslack < self.slack(self.bestedge[b]) {
+                                self.bestedge[b] = k;
+                            }
+                        } else if self.label[w] == 0
+                            && (self.bestedge[w] == NONE || kslack < self.slack(self.bestedge[w]))
+                        {
+                            self.bestedge[w] = k;
+                        }
+                    }
+                }
+                if augmented {
+                    break;
+                }
+
+                // no augmenting path yet, so update the duals to make more edges tight
+                let mut deltatype = 0;
+                let mut delta = 0;
+                let mut deltaedge = NONE;
+                let mut deltablossom = NONE;
+                if !self.max_cardinality {
+                    deltatype = 1;
+                    delta = *self.dualvar[..nvertex].iter().min().unwrap_or(&0);
+                }
+                for v in 0..nvertex {
+                    if self.label[self.inblossom[v]] == 0 && self.bestedge[v] != NONE {
+                        let d = self.slack(self.bestedge[v]);
+                        if deltatype == 0 || d < delta {
+                            delta = d;
+                            deltatype = 2;
+                            deltaedge = self.bestedge[v];
+                        }
+                    }
+                }
+                for b in 0..2 * nvertex {
+                    if self.blossomparent[b] == NONE
+                        && self.label[b] == 1
+                        && self.bestedge[b] != NONE
+                    {
+                        // slack between two S-blossoms is always even for integer weights
+                        let d = self.slack(self.bestedge[b]) / 2;
+                        if deltatype == 0 || d < delta {
+                            delta = d;
+                            deltatype = 3;
+                            deltaedge = self.bestedge[b];
+                        }
+                    }
+                }
+                for b in nvertex..2 * nvertex {
+                    if self.blossombase[b] != NONE
+                        && self.blossomparent[b] == NONE
+                        && self.label[b] == 2
+                        && (deltatype == 0 || self.dualvar[b] < delta)
+                    {
+                        delta = self.dualvar[b];
+                        deltatype = 4;
+                        deltablossom = b;
+                    }
+                }
+                if deltatype == 0 {
+                    // max cardinality optimum reached, do a final update so the duals stay valid
+                    deltatype = 1;
+                    delta = (*self.dualvar[..nvertex].iter().min().unwrap_or(&0)).max(0);
+                }
+
+                for v in 0..nvertex {
+                    match self.label[self.inblossom[v]] {
+                        1 => self.dualvar[v] -= delta,
+                        2 => self.dualvar[v] += delta,
+                        _ => {}
+                    }
+                }
+                for b in nvertex..2 * nvertex {
+                    if self.blossombase[b] != NONE && self.blossomparent[b] == NONE {
+                        match self.label[b] {
+                            1 => self.dualvar[b] += delta,
+                            2 => self.dualvar[b] -= delta,
+                            _ => {}
+                        }
+                    }
+                }
+
+                match deltatype {
+                    1 => break,
+                    2 => {
+                        self.allowedge[deltaedge] = true;
+                        let (mut i, j, _) = self.edges[deltaedge];
+                        if self.label[self.inblossom[i]] == 0 {
+                            i = j;
+                        }
+                        self.queue.push(i);
+                    }
+                    3 => {
+                        self.allowedge[deltaedge] = true;
+                        let (i, _, _) = self.edges[deltaedge];
+                        self.queue.push(i);
+                    }
+                    _ => self.expand_blossom(deltablossom, false),
+                }
+            }
+
+            if !augmented {
+                break;
+            }
+
+            // end of stage, expand all S-blossoms with a zero dual
+            for b in nvertex..2 * nvertex {
+                if self.blossomparent[b] == NONE
+                    && self.blossombase[b] != NONE
+                    && self.label[b] == 1
+                    && self.dualvar[b] == 0
+                {
+                    self.expand_blossom(b, true);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tries every matching to find the best weight, preferring more edges if asked to.
+    fn brute_force(n: usize, w: &[Vec<Option<i64>>], max_cardinality: bool) -> (usize, i64) {
+        fn go(
+            v: usize,
+            used: &mut Vec<bool>,
+            w: &[Vec<Option<i64>>],
+            edges: usize,
+            weight: i64,
+            best: &mut Vec<(usize, i64)>,
+        ) {
+            let n = used.len();
+            if v == n {
+                best.push((edges, weight));
+                return;
+            }
+            if used[v] {
+                go(v + 1, used, w, edges, weight, best);
+                return;
+            }
+            // leave v unmatched
+            go(v + 1, used, w, edges, weight, best);
+            used[v] = true;
+            for u in v + 1..n {
+                if let (false, Some(x)) = (used[u], w[v][u]) {
+                    used[u] = true;
+                    go(v + 1, used, w, edges + 1, weight + x, best);
+                    used[u] = false;
+                }
+            }
+            used[v] = false;
+        }
+        let mut all = Vec::new();
+        go(0, &mut vec![false; n], w, 0, 0, &mut all);
+        if max_cardinality {
+            *all.iter().max().unwrap()
+        } else {
+            let best = all.iter().map(|x| x.1).max().unwrap();
+            (0, best)
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        assert!(max_weight_matching(&[], true).is_empty());
+    }
+
+    #[test]
+    fn test_single_edge() {
+        let mate = max_weight_matching(&[(0, 1, 1)], false);
+        assert_eq!(mate, vec![Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn test_prefers_heavier_edges() {
+        // the middle edge outweighs the outer two, unless we insist on matching everyone
+        let mate = max_weight_matching(&[(0, 1, 5), (1, 2, 13), (2, 3, 7)], false);
+        assert_eq!(mate, vec![None, Some(2), Some(1), None]);
+        let mate = max_weight_matching(&[(0, 1, 5), (1, 2, 13), (2, 3, 7)], true);
+        assert_eq!(mate, vec![Some(1), Some(0), Some(3), Some(2)]);
+    }
+
+    #[test]
+    fn test_blossom() {
+        // a triangle with a tail forces a blossom
+        let edges = [
+            (0, 1, 9),
+            (0, 2, 8),
+            (1, 2, 10),
+            (0, 3, 7),
+            (2, 4, 5),
+            (3, 5, 6),
+        ];
+        let mate = max_weight_matching(&edges, false);
+        let weight: i64 = edges
+            .iter()
+            .filter(|(i, j, _)| mate[*i] == Some(*j))
+            .map(|e| e.2)
+            .sum();
+        let w = weights(6, &edges);
+        assert_eq!(weight, brute_force(6, &w, false).1);
+    }
+
+    fn weights(n: usize, edges: &[(usize, usize, i64)]) -> Vec<Vec<Option<i64>>> {
+        let mut w = vec![vec![None; n]; n];
+        for &(i, j, x) in edges {
+            w[i][j] = Some(x);
+            w[j][i] = Some(x);
+        }
+        w
+    }
+
+    #[test]
+    fn test_random_graphs_against_brute_force() {
+        // simple linear congruential generator, so the test is deterministic
+        let mut seed: u64 = 42;
+        let mut rand = move |m: u64| {
+            seed = seed
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (seed >> 33) % m
+        };
+        for _ in 0..300 {
+            let n = 2 + rand(9) as usize;
+            let mut edges = Vec::new();
+            for i in 0..n {
+                for j in i + 1..n {
+                    if rand(3) > 0 {
+                        edges.push((i, j, 1 + rand(20) as i64));
+                    }
+                }
+            }
+            if edges.is_empty() {
+                continue;
+            }
+            let w = weights(n, &edges);
+            for max_cardinality in [false, true] {
+                let mate = max_weight_matching(&edges, max_cardinality);
+                // the matching must be consistent
+                for (v, m) in mate.iter().enumerate() {
+                    if let Some(u) = m {
+                        assert_eq!(mate[*u], Some(v));
+                        assert!(w[v][*u].is_some());
+                    }
+                }
+                let count = mate.iter().filter(|m| m.is_some()).count() / 2;
+                let weight: i64 = edges
+                    .iter()
+                    .filter(|(i, j, _)| mate[*i] == Some(*j))
+                    .map(|e| e.2)
+                    .sum();
+                let (best_count, best_weight) = brute_force(n, &w, max_cardinality);
+                assert_eq!(weight, best_weight, "edges: {edges:?}");
+                if max_cardinality {
+                    assert_eq!(count, best_count, "edges: {edges:?}");
+                }
+            }
+        }
+    }
+}
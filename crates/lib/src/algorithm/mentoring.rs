@@ -0,0 +1,165 @@
+use super::cost::Costs;
+use super::matching;
+use crate::BuddyError;
+use crate::People;
+use tracing::debug;
+
+/// The attribute that says whether someone is a mentor or a mentee.
+pub(crate) const ROLE: &str = "role";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Mentor,
+    Mentee,
+}
+
+fn role(people: &People, id: usize) -> Result<Role, BuddyError> {
+    match people.attribute(id, ROLE).map(str::to_lowercase).as_deref() {
+        Some("mentor") => Ok(Role::Mentor),
+        Some("mentee") => Ok(Role::Mentee),
+        _ => Err(BuddyError::InvalidRole(id)),
+    }
+}
+
+/// Pairs every mentee with a mentor, preferring pairs that met less often, like the exact solver
+/// does for everyone. Only mentor–mentee pairs are edges in the graph, so two mentors or two
+/// mentees never end up together.
+///
+/// If one side is bigger, as many pairs as possible are formed and the rest of the bigger side sits
+/// this round out, each in a group of their own. Those are the ones who sat out the fewest times
+/// so far, so everyone takes turns.
+pub(crate) fn solve_mentoring(
+    people: &People,
+    ids: &[usize],
+    costs: &Costs,
+) -> Result<Vec<Vec<usize>>, BuddyError> {
+    let roles = ids
+        .iter()
+        .map(|&id| role(people, id))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut edges = Vec::new();
+    for i in 0..ids.len() {
+        for j in i + 1..ids.len() {
+            if roles[i] == roles[j] {
+                continue;
+            }
+            if let Some(cost) = costs.pair(ids[i], ids[j]) {
+                edges.push((i, j, cost as i64));
+            }
+        }
+    }
+    // keep all weights positive, so more pairs always beat leaving people out
+    let top = edges.iter().map(|e| e.2).max().unwrap_or(0) + 1;
+    // every bye of a paired person outweighs all the costs together, so whoever sat out the least
+    // is the one left out, like the evenizer goes to those with the fewest byes
+    let fairness = top * ids.len() as i64;
+    for e in &mut edges {
+        let byes = costs.byes(ids[e.0]) + costs.byes(ids[e.1]);
+        e.2 = top - e.2 + fairness * byes as i64;
+    }
+    let mate = matching::max_weight_matching(&edges, true);
+
+    let mut groups = Vec::new();
+    let mut unpaired = Vec::new();
+    for (i, id) in ids.iter().enumerate() {
+        match mate.get(i).copied().flatten() {
+            Some(j) if i < j => groups.push(vec![*id, ids[j]]),
+            Some(_) => {}
+            None => unpaired.push(vec![*id]),
+        }
+    }
+    // the smaller side should be paired up completely, unless the exclusions got in the way
+    let mentors = roles.iter().filter(|r| **r == Role::Mentor).count();
+    if groups.len() < mentors.min(ids.len() - mentors) {
+        return Err(BuddyError::UnsatisfiableConstraints);
+    }
+    debug!(
+        "Matched {} mentoring pairs, {} people sit out",
+        groups.len(),
+        unpaired.len()
+    );
+    groups.append(&mut unpaired);
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::History;
+    use crate::PairingConfig;
+    use crate::algorithm::history::merge;
+
+    fn people(csv: &str) -> People {
+        People::from_csv(csv.as_bytes()).unwrap()
+    }
+
+    fn solve(people: &People, history: &History) -> Result<Vec<Vec<usize>>, BuddyError> {
+        let config = PairingConfig::new().with_mentoring(true);
+        let costs = Costs::new(people, history, &config);
+        let mut ids = people.as_ids();
+        ids.retain(|id| *id != usize::MAX);
+        solve_mentoring(people, &ids, &costs)
+    }
+
+    #[test]
+    fn test_only_mentor_mentee_pairs() {
+        let people = people("1,A,role=mentor\n2,B,role=mentor\n3,C,role=mentee\n4,D,role=Mentee");
+        let groups = solve(&people, &History::default()).unwrap();
+        assert_eq!(groups.len(), 2);
+        for g in groups {
+            assert_ne!(role(&people, g[0]).unwrap(), role(&people, g[1]).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_rotates_mentors() {
+        let people = people("1,A,role=mentor\n2,B,role=mentor\n3,C,role=mentee\n4,D,role=mentee");
+        let mut history = History::default();
+        merge(&mut history, &[(1, 3), (2, 4)]);
+        let groups = solve(&people, &history).unwrap();
+        assert!(groups.contains(&vec![1, 4]));
+        assert!(groups.contains(&vec![2, 3]));
+    }
+
+    #[test]
+    fn test_unequal_sides() {
+        let people = people(
+            "1,A,role=mentor\n2,B,role=mentee\n3,C,role=mentee\n4,D,role=mentee\n5,E,role=mentor",
+        );
+        let groups = solve(&people, &History::default()).unwrap();
+        let pairs: Vec<_> = groups.iter().filter(|g| g.len() == 2).collect();
+        let alone: Vec<_> = groups.iter().filter(|g| g.len() == 1).collect();
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(alone.len(), 1);
+        assert_eq!(role(&people, alone[0][0]).unwrap(), Role::Mentee);
+    }
+
+    #[test]
+    fn test_takes_turns_sitting_out() {
+        let people = people(
+            "1,A,role=mentor\n2,B,role=mentor\n3,C,role=mentee\n4,D,role=mentee\n5,E,role=mentee",
+        );
+        let mut history = History::default();
+        for round in 1..=6 {
+            let groups = solve(&people, &history).unwrap();
+            let pairs: Vec<_> = groups
+                .iter()
+                .map(|g| (g[0], g.get(1).copied().unwrap_or(usize::MAX)))
+                .collect();
+            merge(&mut history, &pairs);
+            // nobody sits out again before everyone sat out once
+            let byes: Vec<_> = (3..=5).map(|id| history.byes(id)).collect();
+            let (fewest, most) = (byes.iter().min().unwrap(), byes.iter().max().unwrap());
+            assert!(most - fewest <= 1, "byes {byes:?} after round {round}");
+        }
+        assert_eq!((3..=5).map(|id| history.byes(id)).sum::<usize>(), 6);
+    }
+
+    #[test]
+    fn test_missing_role() {
+        let people = people("1,A,role=mentor\n2,B");
+        let r = solve(&people, &History::default());
+        assert!(matches!(r, Err(BuddyError::InvalidRole(2))));
+    }
+}
@@ -1,108 +1,1822 @@
+#[cfg(feature = "bench")]
+pub mod bench;
+mod cost;
+mod heuristic;
 pub mod history;
+mod lookahead;
+mod matching;
+mod mentoring;
+mod outcome;
+mod overlap;
+mod progress;
+mod reroll;
+mod schedule;
+mod scorer;
+#[cfg(test)]
+mod test_util;
+use crate::BuddyError;
+use crate::Exclusions;
 use crate::People;
 use crate::Person;
+use crate::Pins;
+use crate::Preferences;
+use cost::{CostMatrix, Costs};
 use genetic_algorithm::strategy::evolve::prelude::*;
 use history::History;
+use progress::{ProgressFn, Watch};
+use scorer::WeightedScorer;
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::{BTreeSet, HashSet};
+use std::hash::{BuildHasher, Hasher, RandomState};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{debug, trace};
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Pairs(Vec<(Person, Person)>);
+pub use lookahead::plan_ahead;
+pub use outcome::PairingOutcome;
+pub use progress::{CancelToken, Progress};
+pub use reroll::{reroll, reroll_groups};
+pub use schedule::plan_schedule;
+pub use scorer::PairScorer;
 
-impl Pairs {
-    pub fn inner(self) -> Vec<(Person, Person)> {
-        self.0
+/// How many of the worst pairs in the best grouping get left out in turn, for each alternative
+/// asked for. Leaving out every pair would re-solve the whole thing once per person.
+const TRIES_PER_ALTERNATIVE: usize = 3;
+
+/// The groups formed in one round. Each inner list is one group of people meeting together.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Groups {
+    groups: Vec<Vec<Person>>,
+
+    /// The random seed used to find these groups, if the solver needed one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+}
+
+/// Groups of two, the classic buddy pairing.
+pub type Pairs = Groups;
+
+impl Groups {
+    pub(crate) fn new(groups: Vec<Vec<Person>>) -> Self {
+        Self { groups, seed: None }
+    }
+
+    pub fn inner(self) -> Vec<Vec<Person>> {
+        self.groups
+    }
+
+    /// The seed to pass to [`PairingConfig::with_seed`] to get these same groups again.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+}
+
+/// Which algorithm [`pair`] uses to find the groups.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Solver {
+    /// A genetic algorithm. Works for any group size and copes with big rosters, but isn't
+    /// guaranteed to find the best possible pairing.
+    #[default]
+    Genetic,
+    /// A minimum weight perfect matching over the [`History`]. Always finds a pairing with the
+    /// fewest possible repeats, but only works for pairs. Runs in O(n³), which is instant for
+    /// the rosters we usually see.
+    Exact,
+    /// A greedy start improved by swapping people between groups until no swap helps. Works for
+    /// any group size and pairs thousands of people in seconds, but like the genetic algorithm
+    /// isn't guaranteed to find the best possible pairing.
+    Heuristic,
+}
+
+/// How much older meetings in the [`History`] count compared to recent ones.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Decay {
+    /// Every meeting counts the same, no matter how long ago it was.
+    #[default]
+    None,
+    /// A meeting counts half as much for every `half_life` between it and the newest meeting in
+    /// the history, so the same history always gives the same pairs. Meetings from history files
+    /// without a timestamp in their name count fully.
+    HalfLife(Duration),
+    /// Only meetings in the last this many rounds count.
+    Window(usize),
+}
+
+/// What to do with the odd one out when pairing an odd number of people.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OddPolicy {
+    /// Someone sits the round out, taking turns.
+    #[default]
+    SitOut,
+    /// One pair becomes a group of three, picked to repeat as few meetings as possible.
+    Triad,
+}
+
+/// Tuning for the genetic solver in one go, for front ends that let people fill it in. Anything
+/// left at `None` keeps the [`PairingConfig`]'s setting. See
+/// [`PairingConfig::with_genetic_params`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GeneticParams {
+    pub population_size: Option<usize>,
+    pub max_stale_generations: Option<usize>,
+    pub mutation_rate: Option<f32>,
+    pub crossover_rate: Option<f32>,
+    /// The replacement and the elitism rate, which only go together.
+    pub elite_selection: Option<(f32, f32)>,
+    pub time_limit: Option<Duration>,
+}
+
+/// Settings for a single [`pair`] run.
+#[derive(Debug, Clone)]
+pub struct PairingConfig {
+    group_size: usize,
+    solver: Solver,
+    seed: Option<u64>,
+    exclusions: Exclusions,
+    preferences: Preferences,
+    preference_weight: f64,
+    pins: Pins,
+    decay: Decay,
+    cooldown: usize,
+    diversity: Vec<(String, f64)>,
+    mentoring: bool,
+    scorers: Vec<WeightedScorer>,
+    odd_policy: OddPolicy,
+    absent: BTreeSet<usize>,
+    min_overlap: Option<Duration>,
+    population_size: usize,
+    max_stale_generations: usize,
+    mutation_rate: f32,
+    crossover_rate: f32,
+    replacement_rate: f32,
+    elitism_rate: f32,
+    time_limit: Option<Duration>,
+    progress: Option<ProgressFn>,
+    cancel: Option<CancelToken>,
+}
+
+impl Default for PairingConfig {
+    fn default() -> Self {
+        Self {
+            group_size: 2,
+            solver: Solver::default(),
+            seed: None,
+            exclusions: Exclusions::default(),
+            preferences: Preferences::default(),
+            preference_weight: 0.0,
+            pins: Pins::default(),
+            decay: Decay::default(),
+            cooldown: 0,
+            diversity: Vec::new(),
+            mentoring: false,
+            scorers: Vec::new(),
+            odd_policy: OddPolicy::default(),
+            absent: BTreeSet::new(),
+            min_overlap: None,
+            population_size: 50,
+            max_stale_generations: 1000,
+            mutation_rate: 0.2,
+            crossover_rate: 0.9,
+            replacement_rate: 0.5,
+            elitism_rate: 0.5,
+            time_limit: None,
+            progress: None,
+            cancel: None,
+        }
+    }
+}
+
+impl PairingConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many people should end up in each group. Defaults to 2. If the people can't be split
+    /// evenly, some groups will be one smaller than this.
+    #[must_use]
+    pub fn with_group_size(mut self, group_size: usize) -> Self {
+        self.group_size = group_size;
+        self
+    }
+
+    pub fn group_size(&self) -> usize {
+        self.group_size
+    }
+
+    /// Which [`Solver`] to use. Defaults to [`Solver::Genetic`].
+    #[must_use]
+    pub fn with_solver(mut self, solver: Solver) -> Self {
+        self.solver = solver;
+        self
+    }
+
+    pub fn solver(&self) -> Solver {
+        self.solver
+    }
+
+    /// Seeds the random number generator, so the same people and [`History`] always give the
+    /// same groups. Without a seed, a random one is picked and recorded in the resulting
+    /// [`Groups`].
+    #[must_use]
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// People who must never end up in the same group. This is a hard rule: if there's no way
+    /// around it, [`pair`] fails with [`BuddyError::UnsatisfiableConstraints`].
+    #[must_use]
+    pub fn with_exclusions(mut self, exclusions: Exclusions) -> Self {
+        self.exclusions = exclusions;
+        self
+    }
+
+    pub fn exclusions(&self) -> &Exclusions {
+        &self.exclusions
+    }
+
+    /// Favors pairs who'd like to meet each other over pairs who'd rather not. The keenest pair
+    /// costs nothing extra, and every other pair costs `weight` for each point their
+    /// [`mutual`](Preferences::mutual) rating falls short of it. Keep the weight below 0.5 and
+    /// even the keenest pair won't outweigh a repeat.
+    #[must_use]
+    pub fn with_preferences(mut self, preferences: Preferences, weight: f64) -> Self {
+        self.preferences = preferences;
+        self.preference_weight = weight;
+        self
+    }
+
+    pub fn preferences(&self) -> &Preferences {
+        &self.preferences
+    }
+
+    pub fn preference_weight(&self) -> f64 {
+        self.preference_weight
+    }
+
+    /// Pairs that must meet this round no matter what. Each becomes a group of its own, and the
+    /// solver only arranges everyone else.
+    #[must_use]
+    pub fn with_pins(mut self, pins: Pins) -> Self {
+        self.pins = pins;
+        self
+    }
+
+    pub fn pins(&self) -> &Pins {
+        &self.pins
+    }
+
+    /// Lets old meetings count less, so stale pairs become eligible again sooner. Defaults to
+    /// [`Decay::None`].
+    #[must_use]
+    pub fn with_decay(mut self, decay: Decay) -> Self {
+        self.decay = decay;
+        self
+    }
+
+    pub fn decay(&self) -> Decay {
+        self.decay
+    }
+
+    /// Never pairs people who met in the last `rounds` rounds of the [`History`], whatever else
+    /// would speak for it. Unlike the [`Decay`], this is a hard rule like the exclusions, and
+    /// pairing fails if there's no way to keep it. 0, the default, turns it off.
+    #[must_use]
+    pub fn with_cooldown(mut self, rounds: usize) -> Self {
+        self.cooldown = rounds;
+        self
+    }
+
+    pub fn cooldown(&self) -> usize {
+        self.cooldown
+    }
+
+    /// Prefers groups of people with different values for the given attribute, like `team`.
+    /// Every pair sharing a value costs `weight` on top of their [`History`], so a weight of 1.0
+    /// makes a same-team pair as bad as a repeat. Call it again to add more attributes.
+    #[must_use]
+    pub fn with_diversity(mut self, attribute: impl Into<String>, weight: f64) -> Self {
+        self.diversity.push((attribute.into(), weight));
+        self
+    }
+
+    pub fn diversity(&self) -> &[(String, f64)] {
+        &self.diversity
+    }
+
+    /// Only pairs mentors with mentees, going by everyone's `role` attribute, which must be either
+    /// `mentor` or `mentee`. If one side is bigger, the people left over sit the round out. This
+    /// always uses the exact matching, whatever the [`Solver`], and only works for pairs.
+    #[must_use]
+    pub fn with_mentoring(mut self, mentoring: bool) -> Self {
+        self.mentoring = mentoring;
+        self
+    }
+
+    pub fn mentoring(&self) -> bool {
+        self.mentoring
+    }
+
+    /// What to do with the odd one out when pairing an odd number of people. Defaults to
+    /// [`OddPolicy::SitOut`]. Only matters for pairs, and mentoring always lets people sit out.
+    #[must_use]
+    pub fn with_odd_policy(mut self, odd_policy: OddPolicy) -> Self {
+        self.odd_policy = odd_policy;
+        self
+    }
+
+    pub fn odd_policy(&self) -> OddPolicy {
+        self.odd_policy
+    }
+
+    /// People who are in the roster, but away for this round. They're left out as if they weren't
+    /// in the input at all.
+    #[must_use]
+    pub fn with_absent(mut self, absent: impl IntoIterator<Item = usize>) -> Self {
+        self.absent.extend(absent);
+        self
+    }
+
+    pub fn absent(&self) -> &BTreeSet<usize> {
+        &self.absent
+    }
+
+    /// Only puts people together whose working hours overlap at least this long, going by their
+    /// `timezone` attribute, an offset from UTC like `+5:30`, and their local `hours`, like
+    /// `9-17`, which is the default. People without a time zone can meet anyone.
+    #[must_use]
+    pub fn with_min_overlap(mut self, min_overlap: Duration) -> Self {
+        self.min_overlap = Some(min_overlap);
+        self
+    }
+
+    pub fn min_overlap(&self) -> Option<Duration> {
+        self.min_overlap
+    }
+
+    /// How many candidate groupings the genetic algorithm keeps around. Bigger populations search
+    /// more widely, but every generation takes longer. Defaults to 50.
+    #[must_use]
+    pub fn with_population_size(mut self, population_size: usize) -> Self {
+        self.population_size = population_size;
+        self
+    }
+
+    pub fn population_size(&self) -> usize {
+        self.population_size
+    }
+
+    /// The genetic algorithm stops after this many generations without finding anything better.
+    /// Raise it for big groups, lower it to finish small groups instantly. Defaults to 1000.
+    #[must_use]
+    pub fn with_max_stale_generations(mut self, max_stale_generations: usize) -> Self {
+        self.max_stale_generations = max_stale_generations;
+        self
+    }
+
+    pub fn max_stale_generations(&self) -> usize {
+        self.max_stale_generations
+    }
+
+    /// How likely each candidate is to get two people swapped every generation, from 0 to 1.
+    /// Defaults to 0.2.
+    #[must_use]
+    pub fn with_mutation_rate(mut self, mutation_rate: f32) -> Self {
+        self.mutation_rate = mutation_rate;
+        self
+    }
+
+    pub fn mutation_rate(&self) -> f32 {
+        self.mutation_rate
+    }
+
+    /// How much of the population gets picked to make offspring every generation, from 0 to 1.
+    /// Defaults to 0.9.
+    #[must_use]
+    pub fn with_crossover_rate(mut self, crossover_rate: f32) -> Self {
+        self.crossover_rate = crossover_rate;
+        self
+    }
+
+    pub fn crossover_rate(&self) -> f32 {
+        self.crossover_rate
+    }
+
+    /// How the best candidates are selected every generation: the share of offspring that replace
+    /// their parents, and the share of the best parents that are always kept. Both go from 0 to 1
+    /// and default to 0.5.
+    #[must_use]
+    pub fn with_elite_selection(mut self, replacement_rate: f32, elitism_rate: f32) -> Self {
+        self.replacement_rate = replacement_rate;
+        self.elitism_rate = elitism_rate;
+        self
+    }
+
+    pub fn elite_selection(&self) -> (f32, f32) {
+        (self.replacement_rate, self.elitism_rate)
+    }
+
+    /// Stops the genetic algorithm after this long, with the best groups it found so far, even if
+    /// it's still improving. Big groups can take a while otherwise.
+    #[must_use]
+    pub fn with_time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = Some(time_limit);
+        self
+    }
+
+    pub fn time_limit(&self) -> Option<Duration> {
+        self.time_limit
+    }
+
+    /// Sets whatever is filled in of the [`GeneticParams`], like calling
+    /// [`with_population_size`](Self::with_population_size) and the others one by one.
+    #[must_use]
+    pub fn with_genetic_params(mut self, params: GeneticParams) -> Self {
+        if let Some(size) = params.population_size {
+            self.population_size = size;
+        }
+        if let Some(generations) = params.max_stale_generations {
+            self.max_stale_generations = generations;
+        }
+        if let Some(rate) = params.mutation_rate {
+            self.mutation_rate = rate;
+        }
+        if let Some(rate) = params.crossover_rate {
+            self.crossover_rate = rate;
+        }
+        if let Some((replacement, elitism)) = params.elite_selection {
+            self.replacement_rate = replacement;
+            self.elitism_rate = elitism;
+        }
+        if params.time_limit.is_some() {
+            self.time_limit = params.time_limit;
+        }
+        self
+    }
+
+    /// Calls `progress` after every generation of the genetic algorithm, with how far it got.
+    /// It runs on the pairing thread, so keep it quick.
+    #[must_use]
+    pub fn with_progress(mut self, progress: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(ProgressFn(Arc::new(progress)));
+        self
+    }
+
+    /// Lets another thread stop the pairing with the [`CancelToken`], which then fails with
+    /// [`BuddyError::Cancelled`].
+    #[must_use]
+    pub fn with_cancel(mut self, cancel: CancelToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Makes sure the genetic algorithm settings make sense before running it.
+    fn check_genetic(&self) -> Result<(), BuddyError> {
+        if self.population_size < 2 {
+            return Err(BuddyError::InvalidSolverParameter(
+                "the population size needs to be at least 2".to_string(),
+            ));
+        }
+        for (name, rate) in [
+            ("mutation rate", self.mutation_rate),
+            ("crossover rate", self.crossover_rate),
+            ("replacement rate", self.replacement_rate),
+            ("elitism rate", self.elitism_rate),
+        ] {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(BuddyError::InvalidSolverParameter(format!(
+                    "the {name} needs to be between 0 and 1, not {rate}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a custom [`PairScorer`] on top of the [`History`]. Its scores are multiplied by
+    /// `weight` before being added up. Call it again to add more scorers.
+    #[must_use]
+    pub fn with_scorer(mut self, scorer: impl PairScorer + 'static, weight: f64) -> Self {
+        self.scorers.push(WeightedScorer {
+            scorer: Arc::new(scorer),
+            weight,
+        });
+        self
     }
 }
 
 /// This function does the pairing magic. Given some [`People`] and a [`History`] of past pairings,
-/// it'll output a new set of [`Pairs`].
-pub fn pair(people: People, last: &History) -> Pairs {
-    let ids = people.as_ids();
+/// it'll output a new set of [`Groups`], sized according to the [`PairingConfig`], along with how
+/// good they are in a [`PairingOutcome`].
+///
+/// Fails with a [`BuddyError`] instead of panicking when there's nothing sensible to do: nobody
+/// or only one person to pair, rules that can't all be kept, or a solver that gives up.
+pub fn pair(
+    people: People,
+    last: &History,
+    config: &PairingConfig,
+) -> Result<PairingOutcome, BuddyError> {
+    let mut outcomes = pair_alternatives(people, last, config, 1)?;
+    Ok(outcomes.remove(0))
+}
+
+/// Like [`pair`], but comes up with up to `count` different groupings to choose from, best first.
+/// The first one is what [`pair`] would have picked, and asking for none still gets you that.
+///
+/// The genetic algorithm offers the best distinct groupings left in its population at the end.
+/// The exact matching takes its best pairs, and then finds the best pairs without each of the
+/// ones that cost the most in turn. Either way, there may be fewer than `count` if that's all
+/// there is.
+pub fn pair_alternatives(
+    people: People,
+    last: &History,
+    config: &PairingConfig,
+    count: usize,
+) -> Result<Vec<PairingOutcome>, BuddyError> {
+    pair_rejecting(people, last, config, count, &[])
+}
+
+/// Like [`pair_alternatives`], but the `rejected` pairs only meet when there's no other way. They
+/// didn't really meet, so the cooldown and the decay still only go by `last`.
+fn pair_rejecting(
+    people: People,
+    last: &History,
+    config: &PairingConfig,
+    count: usize,
+    rejected: &[(usize, usize)],
+) -> Result<Vec<PairingOutcome>, BuddyError> {
+    let start = Instant::now();
+    let count = count.max(1);
+    if config.group_size < 2 {
+        return Err(BuddyError::InvalidGroupSize(config.group_size));
+    }
+
+    let people = people.without(&config.absent)?;
+    check_roster(&people)?;
+    check_working_hours(&people, config)?;
+    let mut costs = Costs::new(&people, last, config);
+    for &(i, j) in rejected {
+        costs.reject(i, j);
+    }
+    let pinned = pinned_groups(&people, &costs, config)?;
+
+    if config.mentoring && config.group_size != 2 {
+        return Err(BuddyError::MentoringNeedsPairs(config.group_size));
+    }
+
+    let triad = config.group_size == 2 && config.odd_policy == OddPolicy::Triad;
+    let mut ids = people.as_ids();
+    // The evenizer only makes sense for pairs. Bigger groups just get smaller instead, and
+    // mentoring leaves people out anyway when the sides don't match up.
+    if config.group_size > 2 || config.mentoring || triad {
+        ids.retain(|id| *id != usize::MAX);
+    }
+    ids.retain(|id| !pinned.iter().flatten().any(|p| p == id));
+
+    let mut seed = None;
+    let mut generations = None;
+    let candidates = match config.solver {
+        // everyone's pinned, nothing left to arrange
+        _ if ids.is_empty() => vec![Vec::new()],
+        _ if config.mentoring => alternatives(&costs, count, |costs| {
+            mentoring::solve_mentoring(&people, &ids, costs)
+        })?,
+        Solver::Genetic => {
+            config.check_genetic()?;
+            // for pairs we can tell up front whether the hard rules leave a way to pair everyone
+            if config.group_size == 2 && costs.has_forbidden() {
+                solve_exact(&ids, &costs)?;
+            }
+            let sizes = if triad {
+                triad_sizes(ids.len())
+            } else {
+                group_sizes(ids.len(), config.group_size)
+            };
+            let s = config.seed.unwrap_or_else(random_seed);
+            debug!("Using seed {s}");
+            seed = Some(s);
+            let watch = Watch::new(config);
+            let (candidates, generations_run) =
+                solve_genetic(&ids, &sizes, &costs, config, s, &watch)?;
+            if watch.is_cancelled() {
+                return Err(BuddyError::Cancelled);
+            }
+            generations = Some(generations_run);
+            let ranked = rank(candidates, &costs, count);
+            // Stopping early can leave nothing that keeps the rules, which doesn't mean there's
+            // no way to. For pairs, the exact solver finds one in no time.
+            if ranked.is_empty() && watch.should_stop() {
+                if config.group_size != 2 {
+                    return Err(BuddyError::OutOfTime);
+                }
+                debug!("Out of time without valid groups, falling back to the exact solver");
+                if triad && !ids.len().is_multiple_of(2) && ids.len() > 1 {
+                    alternatives(&costs, 1, |costs| solve_exact_triad(&ids, costs))?
+                } else {
+                    alternatives(&costs, 1, |costs| solve_exact(&ids, costs))?
+                }
+            } else {
+                ranked
+            }
+        }
+        Solver::Heuristic => {
+            let sizes = if triad {
+                triad_sizes(ids.len())
+            } else {
+                group_sizes(ids.len(), config.group_size)
+            };
+            let watch = Watch::new(config);
+            let candidates = alternatives(&costs, count, |costs| {
+                Ok(heuristic::solve_heuristic(&ids, &sizes, costs, &watch))
+            })?;
+            if watch.is_cancelled() {
+                return Err(BuddyError::Cancelled);
+            }
+            candidates
+        }
+        Solver::Exact => {
+            if config.group_size != 2 {
+                return Err(BuddyError::ExactSolverNeedsPairs(config.group_size));
+            }
+            if triad && !ids.len().is_multiple_of(2) && ids.len() > 1 {
+                alternatives(&costs, count, |costs| solve_exact_triad(&ids, costs))?
+            } else {
+                alternatives(&costs, count, |costs| solve_exact(&ids, costs))?
+            }
+        }
+    };
+    // the best one breaking the rules means there's no way to keep them
+    if candidates.is_empty() {
+        return Err(BuddyError::UnsatisfiableConstraints);
+    }
+
+    let elapsed = start.elapsed();
+    candidates
+        .iter()
+        .map(|candidate| {
+            let groups: Vec<Vec<usize>> = pinned.iter().chain(candidate).cloned().collect();
+            let groups = Groups {
+                groups: to_people(&people, &groups)?,
+                seed,
+            };
+            Ok(PairingOutcome::new(
+                groups,
+                last,
+                &costs,
+                generations,
+                elapsed,
+            ))
+        })
+        .collect()
+}
+
+/// Makes sure there's anyone to pair at all.
+fn check_roster(people: &People) -> Result<(), BuddyError> {
+    match people.len() {
+        0 => Err(BuddyError::EmptyRoster),
+        n if n < 2 => Err(BuddyError::TooFewPeople(n)),
+        _ => Ok(()),
+    }
+}
+
+/// Puts names to the IDs in the groups a solver came up with.
+fn to_people(people: &People, groups: &[Vec<usize>]) -> Result<Vec<Vec<Person>>, BuddyError> {
+    groups
+        .iter()
+        .map(|group| {
+            group
+                .iter()
+                .map(|id| {
+                    let name = people.name_from_id(*id).ok_or_else(|| {
+                        BuddyError::SolverFailure(format!("it made up a person with ID {id}"))
+                    })?;
+                    Ok(Person::new(*id, name))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Finds up to `count` groupings with an exact `solve`: its best one, and then the best ones
+/// without each of its worst pairs.
+fn alternatives(
+    costs: &Costs,
+    count: usize,
+    solve: impl Fn(&Costs) -> Result<Vec<Vec<usize>>, BuddyError>,
+) -> Result<Vec<Vec<Vec<usize>>>, BuddyError> {
+    let best = solve(costs)?;
+    let mut candidates = vec![best.clone()];
+    if count > 1 {
+        let mut pairs: Vec<(usize, usize)> = best
+            .iter()
+            .flat_map(|group| {
+                group
+                    .iter()
+                    .enumerate()
+                    .flat_map(move |(n, &i)| group[n + 1..].iter().map(move |&j| (i, j)))
+            })
+            .collect();
+        // the pairs that cost the most are the ones worth doing without
+        pairs.sort_by_key(|&(i, j)| std::cmp::Reverse(costs.pair(i, j)));
+        for (i, j) in pairs.into_iter().take(count * TRIES_PER_ALTERNATIVE) {
+            let mut without = costs.clone();
+            without.forbid(i, j);
+            // some pairs can't be done without
+            if let Ok(groups) = solve(&without) {
+                candidates.push(groups);
+            }
+        }
+    }
+    Ok(rank(candidates, costs, count))
+}
+
+/// Puts the first `count` distinct groupings that keep the rules in order, best first. Ties keep
+/// the order they came in, so the solver's favorite stays on top.
+fn rank(candidates: Vec<Vec<Vec<usize>>>, costs: &Costs, count: usize) -> Vec<Vec<Vec<usize>>> {
+    let mut seen = HashSet::new();
+    let mut ranked: Vec<(isize, Vec<Vec<usize>>)> = candidates
+        .into_iter()
+        .filter(|groups| groups.iter().all(|g| costs.is_allowed(g)))
+        .filter(|groups| {
+            // the same groups in a different order are no alternative
+            let mut key: Vec<Vec<usize>> = groups.clone();
+            key.iter_mut().for_each(|g| g.sort_unstable());
+            key.sort_unstable();
+            seen.insert(key)
+        })
+        .map(|groups| (groups.iter().map(|g| costs.group(g)).sum(), groups))
+        .collect();
+    ranked.sort_by_key(|(score, _)| *score);
+    ranked
+        .into_iter()
+        .take(count)
+        .map(|(_, groups)| groups)
+        .collect()
+}
 
+/// Makes sure everyone's working hours can be read, if we need them.
+fn check_working_hours(people: &People, config: &PairingConfig) -> Result<(), BuddyError> {
+    if config.min_overlap.is_some() {
+        overlap::working_hours(people)?;
+    }
+    Ok(())
+}
+
+/// Checks the pinned pairs make sense for these people and turns them into groups.
+fn pinned_groups(
+    people: &People,
+    costs: &Costs,
+    config: &PairingConfig,
+) -> Result<Vec<Vec<usize>>, BuddyError> {
+    let mut seen = Vec::new();
+    let mut groups = Vec::new();
+    for &(a, b) in config.pins.iter() {
+        for id in [a, b] {
+            if config.absent.contains(&id) {
+                return Err(BuddyError::PinnedAbsent(id));
+            }
+            if !people.contains(id) {
+                return Err(BuddyError::UnknownPinnedPerson(id));
+            }
+            if seen.contains(&id) {
+                return Err(BuddyError::PinnedTwice(id));
+            }
+            seen.push(id);
+        }
+        if !costs.is_allowed(&[a, b]) {
+            return Err(BuddyError::UnsatisfiableConstraints);
+        }
+        groups.push(vec![a, b]);
+    }
+    Ok(groups)
+}
+
+/// Picks a fresh seed. The standard library seeds each `RandomState` randomly, so hashing nothing
+/// with it is a cheap source of randomness.
+fn random_seed() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// One way of splitting people into groups, by ID.
+type Grouping = Vec<Vec<usize>>;
+
+/// Runs the genetic algorithm over all orderings of `ids`, cutting each ordering into groups of
+/// the given sizes. Also returns how many generations it took.
+fn solve_genetic(
+    ids: &[usize],
+    sizes: &[usize],
+    costs: &Costs,
+    config: &PairingConfig,
+    seed: u64,
+    watch: &Watch<UniqueGenotype<usize>>,
+) -> Result<(Vec<Grouping>, usize), BuddyError> {
+    // the genes are positions in `ids`, which the cost matrix is laid out by
     let genotype = UniqueGenotype::builder()
-        .with_allele_list(ids)
+        .with_allele_list((0..ids.len()).collect())
         .build()
-        .unwrap();
+        .map_err(|e| BuddyError::SolverFailure(e.0.to_string()))?;
 
     debug!("{genotype}");
 
     let mut evolve = Evolve::builder()
         .with_genotype(genotype)
-        .with_target_population_size(50)
-        .with_max_stale_generations(1000)
-        .with_fitness(PairFitness::new(last.clone()))
+        .with_target_population_size(config.population_size)
+        .with_max_stale_generations(config.max_stale_generations)
+        .with_fitness(PairFitness::new(
+            CostMatrix::new(ids, costs),
+            sizes.to_vec(),
+        ))
         .with_fitness_ordering(FitnessOrdering::Minimize)
         .with_target_fitness_score(0)
-        //.with_par_fitness(true)
+        .with_par_fitness(true)
         .with_replace_on_equal_fitness(true)
-        .with_mutate(MutateSingleGene::new(0.2))
-        .with_crossover(CrossoverClone::new(0.9))
-        .with_select(SelectElite::new(0.5, 0.5))
-        //.with_reporter(EvolveReporterSimple::new(1000))
+        .with_mutate(MutateSingleGene::new(config.mutation_rate))
+        .with_crossover(CrossoverClone::new(config.crossover_rate))
+        .with_select(SelectElite::new(
+            config.replacement_rate,
+            config.elitism_rate,
+        ))
+        .with_extension(watch.clone())
+        .with_rng_seed_from_u64(seed)
+        .with_reporter(FinalPopulation::default())
         .build()
-        .unwrap();
+        .map_err(|e| BuddyError::SolverFailure(e.0.to_string()))?;
 
     evolve.call();
     let genes = evolve
         .best_genes()
-        .expect("Something went wrong getting best genes");
+        .ok_or_else(|| BuddyError::SolverFailure("it found no pairs at all".to_string()))?;
 
-    let pairs: Vec<(usize, usize)> = genes.chunks(2).map(|c| (c[0], c[1])).collect();
-    let pairs: Vec<(Person, Person)> = pairs
-        .iter()
-        .map(|(id1, id2)| {
-            (
-                Person::new(*id1, people.name_from_id(*id1).unwrap()),
-                Person::new(*id2, people.name_from_id(*id2).unwrap()),
-            )
+    // the best ever first, then whatever else was left in the population
+    let candidates = std::iter::once(&genes)
+        .chain(&evolve.reporter.genes)
+        .map(|genes| {
+            split(genes, sizes)
+                .map(|group| group.iter().map(|&n| ids[n]).collect())
+                .collect()
         })
         .collect();
-    Pairs(pairs)
+    Ok((candidates, evolve.state.current_generation))
+}
+
+/// Holds on to the final population of the genetic algorithm, which it throws away when done.
+#[derive(Clone, Default)]
+struct FinalPopulation {
+    genes: Vec<Vec<usize>>,
+}
+
+impl StrategyReporter for FinalPopulation {
+    type Genotype = UniqueGenotype<usize>;
+
+    fn on_finish<S: StrategyState<Self::Genotype>, C: StrategyConfig>(
+        &mut self,
+        _genotype: &Self::Genotype,
+        state: &S,
+        _config: &C,
+    ) {
+        let population = &state.population_as_ref().chromosomes;
+        self.genes = population.iter().map(|c| c.genes.clone()).collect();
+    }
+}
+
+/// Finds the pairs with the lowest total [`History`] count by turning it into a maximum weight
+/// matching: every allowed pair becomes an edge, and pairs that met less often weigh more.
+fn solve_exact(ids: &[usize], costs: &Costs) -> Result<Vec<Vec<usize>>, BuddyError> {
+    let mut edges = Vec::new();
+    for i in 0..ids.len() {
+        for j in i + 1..ids.len() {
+            if let Some(cost) = costs.pair(ids[i], ids[j]) {
+                edges.push((i, j, cost as i64));
+            }
+        }
+    }
+    // keep all weights positive, so a full matching always beats leaving people out
+    let top = edges.iter().map(|e| e.2).max().unwrap_or(0) + 1;
+    for e in &mut edges {
+        e.2 = top - e.2;
+    }
+    let mate = matching::max_weight_matching(&edges, true);
+
+    let mut groups = Vec::new();
+    for (i, id) in ids.iter().enumerate() {
+        match mate.get(i).copied().flatten() {
+            Some(j) if i < j => groups.push(vec![*id, ids[j]]),
+            Some(_) => {}
+            None => groups.push(vec![*id]),
+        }
+    }
+    // with an odd number of people one is always left over, anything more means the exclusions
+    // got in the way
+    if groups.len() > ids.len().div_ceil(2) {
+        return Err(BuddyError::UnsatisfiableConstraints);
+    }
+    debug!("Exact solver matched {} groups", groups.len());
+    Ok(groups)
+}
+
+/// Pairs an odd number of people with one group of three. Every person gets a turn at being the
+/// third: everyone else is matched exactly, and the third joins whichever pair suits them best.
+/// The cheapest of those wins.
+fn solve_exact_triad(ids: &[usize], costs: &Costs) -> Result<Vec<Vec<usize>>, BuddyError> {
+    let mut best: Option<(isize, Vec<Vec<usize>>)> = None;
+    for (n, &third) in ids.iter().enumerate() {
+        let rest: Vec<usize> = ids.iter().copied().filter(|&id| id != third).collect();
+        // some people might not fit anywhere without breaking an exclusion, try the next one
+        let Ok(mut groups) = solve_exact(&rest, costs) else {
+            continue;
+        };
+        let Some((g, _)) = groups
+            .iter()
+            .enumerate()
+            .filter_map(|(g, pair)| {
+                let with = [pair[0], pair[1], third];
+                costs
+                    .is_allowed(&with)
+                    .then(|| (g, costs.group(&with) - costs.group(pair)))
+            })
+            .min_by_key(|&(_, extra)| extra)
+        else {
+            continue;
+        };
+        groups[g].push(third);
+        let total = groups.iter().map(|group| costs.group(group)).sum();
+        if best.as_ref().is_none_or(|(score, _)| total < *score) {
+            debug!("Triad with {third} as the third person (#{n}) scores {total}");
+            best = Some((total, groups));
+        }
+    }
+    best.map(|(_, groups)| groups)
+        .ok_or(BuddyError::UnsatisfiableConstraints)
+}
+
+/// Works out how big each group is when splitting `people` into groups of at most `group_size`.
+/// We use as few groups as possible and spread the people evenly, so sizes differ by one at most.
+fn group_sizes(people: usize, group_size: usize) -> Vec<usize> {
+    let groups = people.div_ceil(group_size);
+    if groups == 0 {
+        return Vec::new();
+    }
+    let (base, extra) = (people / groups, people % groups);
+    (0..groups)
+        .map(|i| if i < extra { base + 1 } else { base })
+        .collect()
+}
+
+/// Group sizes for pairs, where an odd one out joins the first pair instead of sitting out.
+fn triad_sizes(people: usize) -> Vec<usize> {
+    if people < 3 {
+        return group_sizes(people, 2);
+    }
+    let mut sizes = vec![2; people / 2];
+    if !people.is_multiple_of(2) {
+        sizes[0] = 3;
+    }
+    sizes
+}
+
+/// Splits a list of genes into consecutive groups of the given sizes.
+fn split<'a>(genes: &'a [usize], sizes: &'a [usize]) -> impl Iterator<Item = &'a [usize]> {
+    sizes.iter().scan(0, |start, size| {
+        let group = &genes[*start..*start + size];
+        *start += size;
+        Some(group)
+    })
 }
 
 #[derive(Clone, Debug)]
 struct PairFitness {
-    last: History,
+    costs: CostMatrix,
+    sizes: Vec<usize>,
 }
 
 impl PairFitness {
-    fn new(last: History) -> PairFitness {
-        Self { last }
+    fn new(costs: CostMatrix, sizes: Vec<usize>) -> PairFitness {
+        Self { costs, sizes }
     }
 }
 impl Fitness for PairFitness {
     type Genotype = UniqueGenotype<usize>;
-    #[allow(clippy::cast_possible_wrap)]
     fn calculate_for_chromosome(
         &mut self,
         chromosome: &FitnessChromosome<Self>,
         _genotype: &FitnessGenotype<Self>,
     ) -> Option<FitnessValue> {
-        let mut score = 0;
-        chromosome.genes().chunks(2).for_each(|chunk| {
-            let (i, j) = (chunk[0], chunk[1]);
-
-            let last = match self.last.get((i, j)) {
-                Some(x) => {
-                    trace!("Found score {x} for pair ({i}, {j}).");
-                    x
-                }
-                None => {
-                    if let Some(x) = self.last.get((j, i)) {
-                        trace!("Found score {x} for pair ({j}, {i}).");
-                        x
-                    } else {
-                        trace!("Found no score for pair ({j}, {i}), using 0");
-                        0
+        // every pair within a group counts as having met
+        let score = split(chromosome.genes(), &self.sizes)
+            .map(|group| self.costs.group(group))
+            .sum();
+        trace!("Score for chromosome {:?}: {score}", chromosome.genes());
+        Some(score)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_group_sizes_even() {
+        assert_eq!(group_sizes(8, 2), vec![2, 2, 2, 2]);
+        assert_eq!(group_sizes(8, 4), vec![4, 4]);
+    }
+
+    #[test]
+    fn test_group_sizes_uneven() {
+        assert_eq!(group_sizes(10, 4), vec![4, 3, 3]);
+        assert_eq!(group_sizes(7, 3), vec![3, 2, 2]);
+        assert_eq!(group_sizes(2, 4), vec![2]);
+        assert!(group_sizes(0, 2).is_empty());
+    }
+
+    #[test]
+    fn test_triad_sizes() {
+        assert_eq!(triad_sizes(7), vec![3, 2, 2]);
+        assert_eq!(triad_sizes(6), vec![2, 2, 2]);
+        assert_eq!(triad_sizes(1), vec![1]);
+    }
+
+    #[test]
+    fn test_split() {
+        let genes = vec![1, 2, 3, 4, 5];
+        let groups: Vec<&[usize]> = split(&genes, &[3, 2]).collect();
+        assert_eq!(groups, vec![&[1, 2, 3][..], &[4, 5][..]]);
+    }
+
+    #[test]
+    fn test_pair_groups_of_three() {
+        let people = People::from_csv("1,A\n2,B\n3,C\n4,D\n5,E\n6,F".as_bytes()).unwrap();
+        let config = PairingConfig::new().with_group_size(3);
+        let groups = pair(people, &History::default(), &config)
+            .unwrap()
+            .into_groups()
+            .inner();
+        assert_eq!(groups.len(), 2);
+        assert!(groups.iter().all(|g| g.len() == 3));
+    }
+
+    #[test]
+    fn test_pair_groups_drop_evenizer() {
+        let people = People::from_csv("1,A\n2,B\n3,C\n4,D\n5,E".as_bytes()).unwrap();
+        let config = PairingConfig::new().with_group_size(3);
+        let groups = pair(people, &History::default(), &config)
+            .unwrap()
+            .into_groups()
+            .inner();
+        assert_eq!(groups.len(), 2);
+        assert!(groups.iter().flatten().all(|p| p.id != usize::MAX));
+    }
+
+    #[test]
+    fn test_exact_avoids_repeats() {
+        let people = People::from_csv("1,A\n2,B\n3,C\n4,D\n5,E\n6,F".as_bytes()).unwrap();
+        let mut history = History::default();
+        // three of the five possible rounds already happened, so two repeat free rounds remain
+        for round in [
+            [(1, 2), (3, 4), (5, 6)],
+            [(1, 3), (2, 5), (4, 6)],
+            [(1, 4), (2, 6), (3, 5)],
+        ] {
+            history::merge(&mut history, &round);
+        }
+        let config = PairingConfig::new().with_solver(Solver::Exact);
+        let pairs = pair(people, &history, &config)
+            .unwrap()
+            .into_groups()
+            .inner();
+        assert_eq!(pairs.len(), 3);
+        for p in pairs {
+            assert_eq!(history.get((p[0].id, p[1].id)), None);
+        }
+    }
+
+    #[test]
+    fn test_heuristic() {
+        let people = test_util::people(200);
+        let mut history = History::default();
+        for round in plan_schedule(people.clone(), &History::default(), 20).unwrap() {
+            let pairs: Vec<(usize, usize)> =
+                round.inner().iter().map(|g| (g[0].id, g[1].id)).collect();
+            history::merge(&mut history, &pairs);
+        }
+        let config = PairingConfig::new().with_solver(Solver::Heuristic);
+        let outcome = pair(people.clone(), &history, &config).unwrap();
+        assert_eq!(outcome.groups().clone().inner().len(), 100);
+        assert_eq!(outcome.repeats(), 0);
+        assert_eq!(outcome.generations(), None);
+
+        let config = config.with_group_size(3);
+        let groups = pair(people, &history, &config)
+            .unwrap()
+            .into_groups()
+            .inner();
+        let mut sizes: Vec<usize> = groups.iter().map(Vec::len).collect();
+        sizes.sort_unstable();
+        assert_eq!(sizes, [vec![2], vec![3; 66]].concat());
+    }
+
+    #[test]
+    fn test_exact_odd_people() {
+        let people = People::from_csv("1,A\n2,B\n3,C".as_bytes()).unwrap();
+        let config = PairingConfig::new().with_solver(Solver::Exact);
+        let pairs = pair(people, &History::default(), &config)
+            .unwrap()
+            .into_groups()
+            .inner();
+        assert_eq!(pairs.len(), 2);
+        assert!(pairs.iter().all(|p| p.len() == 2));
+    }
+
+    #[test]
+    fn test_exact_needs_pairs() {
+        let people = People::from_csv("1,A\n2,B\n3,C".as_bytes()).unwrap();
+        let config = PairingConfig::new()
+            .with_solver(Solver::Exact)
+            .with_group_size(3);
+        let r = pair(people, &History::default(), &config);
+        assert!(matches!(r, Err(BuddyError::ExactSolverNeedsPairs(3))));
+    }
+
+    #[test]
+    fn test_same_seed_same_groups() {
+        let csv = "1,A\n2,B\n3,C\n4,D\n5,E\n6,F\n7,G\n8,H\n9,I\n10,J";
+        let mut history = History::default();
+        history::merge(&mut history, &[(1, 2), (3, 4), (5, 6), (7, 8), (9, 10)]);
+        let run = |config: &PairingConfig| {
+            let people = People::from_csv(csv.as_bytes()).unwrap();
+            pair(people, &history, config).unwrap().into_groups()
+        };
+        let config = PairingConfig::new().with_seed(7);
+        let first = run(&config);
+        let second = run(&config);
+        assert_eq!(first.seed(), Some(7));
+        assert_eq!(first.inner(), second.inner());
+    }
+
+    #[test]
+    fn test_random_seed_is_recorded() {
+        let people = People::from_csv("1,A\n2,B\n3,C\n4,D".as_bytes()).unwrap();
+        let groups = pair(people.clone(), &History::default(), &PairingConfig::new())
+            .unwrap()
+            .into_groups();
+        let seed = groups.seed().unwrap();
+        let again = pair(
+            people,
+            &History::default(),
+            &PairingConfig::new().with_seed(seed),
+        )
+        .unwrap()
+        .into_groups();
+        assert_eq!(groups.inner(), again.inner());
+    }
+
+    fn exclusions(pairs: &[(usize, usize)]) -> Exclusions {
+        let mut exclusions = Exclusions::new();
+        for (a, b) in pairs {
+            exclusions.insert(*a, *b);
+        }
+        exclusions
+    }
+
+    #[test]
+    fn test_exclusions_respected() {
+        let csv = "1,A\n2,B\n3,C\n4,D";
+        // only 1-4 and 2-3 are left
+        let excluded = exclusions(&[(1, 2), (1, 3)]);
+        for solver in [Solver::Genetic, Solver::Exact, Solver::Heuristic] {
+            let people = People::from_csv(csv.as_bytes()).unwrap();
+            let config = PairingConfig::new()
+                .with_solver(solver)
+                .with_exclusions(excluded.clone());
+            let pairs = pair(people, &History::default(), &config)
+                .unwrap()
+                .into_groups()
+                .inner();
+            for p in pairs {
+                assert!(!excluded.contains(p[0].id, p[1].id));
+            }
+        }
+    }
+
+    #[test]
+    fn test_exclusions_unsatisfiable() {
+        let csv = "1,A\n2,B\n3,C\n4,D";
+        // nobody can meet 1
+        let excluded = exclusions(&[(1, 2), (1, 3), (1, 4)]);
+        for solver in [Solver::Genetic, Solver::Exact, Solver::Heuristic] {
+            let people = People::from_csv(csv.as_bytes()).unwrap();
+            let config = PairingConfig::new()
+                .with_solver(solver)
+                .with_exclusions(excluded.clone());
+            let r = pair(people, &History::default(), &config);
+            assert!(matches!(r, Err(BuddyError::UnsatisfiableConstraints)));
+        }
+    }
+
+    #[test]
+    fn test_exclusions_in_groups() {
+        let people = People::from_csv("1,A\n2,B\n3,C\n4,D\n5,E\n6,F".as_bytes()).unwrap();
+        let config = PairingConfig::new()
+            .with_group_size(3)
+            .with_exclusions(exclusions(&[(1, 2), (3, 4)]));
+        let groups = pair(people, &History::default(), &config)
+            .unwrap()
+            .into_groups()
+            .inner();
+        for g in groups {
+            let ids: Vec<usize> = g.iter().map(|p| p.id).collect();
+            assert!(!(ids.contains(&1) && ids.contains(&2)));
+            assert!(!(ids.contains(&3) && ids.contains(&4)));
+        }
+    }
+
+    fn pins(pairs: &[(usize, usize)]) -> Pins {
+        let mut pins = Pins::new();
+        for (a, b) in pairs {
+            pins.insert(*a, *b);
+        }
+        pins
+    }
+
+    #[test]
+    fn test_pins_kept() {
+        let csv = "1,A\n2,B\n3,C\n4,D\n5,E\n6,F";
+        let mut history = History::default();
+        history::merge(&mut history, &[(1, 2)]);
+        for solver in [Solver::Genetic, Solver::Exact, Solver::Heuristic] {
+            let people = People::from_csv(csv.as_bytes()).unwrap();
+            // pinned even though they met before
+            let config = PairingConfig::new()
+                .with_solver(solver)
+                .with_pins(pins(&[(1, 2)]));
+            let pairs = pair(people, &history, &config)
+                .unwrap()
+                .into_groups()
+                .inner();
+            assert_eq!(pairs.len(), 3);
+            let ids: Vec<Vec<usize>> = pairs
+                .iter()
+                .map(|p| p.iter().map(|p| p.id).collect())
+                .collect();
+            assert!(ids.contains(&vec![1, 2]));
+        }
+    }
+
+    #[test]
+    fn test_all_pinned() {
+        let people = People::from_csv("1,A\n2,B".as_bytes()).unwrap();
+        let config = PairingConfig::new().with_pins(pins(&[(2, 1)]));
+        let pairs = pair(people, &History::default(), &config)
+            .unwrap()
+            .into_groups()
+            .inner();
+        assert_eq!(pairs.len(), 1);
+    }
+
+    #[test]
+    fn test_pins_invalid() {
+        let csv = "1,A\n2,B\n3,C\n4,D";
+        let run = |config: PairingConfig| {
+            let people = People::from_csv(csv.as_bytes()).unwrap();
+            pair(people, &History::default(), &config)
+        };
+        let r = run(PairingConfig::new().with_pins(pins(&[(1, 9)])));
+        assert!(matches!(r, Err(BuddyError::UnknownPinnedPerson(9))));
+        let r = run(PairingConfig::new().with_pins(pins(&[(1, 2), (3, 1)])));
+        assert!(matches!(r, Err(BuddyError::PinnedTwice(1))));
+        let r = run(PairingConfig::new()
+            .with_pins(pins(&[(1, 2)]))
+            .with_exclusions(exclusions(&[(1, 2)])));
+        assert!(matches!(r, Err(BuddyError::UnsatisfiableConstraints)));
+    }
+
+    #[test]
+    fn test_window_forgets_old_rounds() {
+        let people = People::from_csv("1,A\n2,B\n3,C\n4,D".as_bytes()).unwrap();
+        let mut history = History::default();
+        // 1-2 and 3-4 met long ago, the other two pairings more recently
+        history::merge(&mut history, &[(1, 2), (3, 4)]);
+        history::merge(&mut history, &[(1, 2), (3, 4)]);
+        history::merge(&mut history, &[(1, 3), (2, 4)]);
+        history::merge(&mut history, &[(1, 4), (2, 3)]);
+        let config = PairingConfig::new()
+            .with_solver(Solver::Exact)
+            .with_decay(Decay::Window(2));
+        let pairs = pair(people, &history, &config)
+            .unwrap()
+            .into_groups()
+            .inner();
+        for p in pairs {
+            assert!([(1, 2), (3, 4)].contains(&(p[0].id, p[1].id)));
+        }
+    }
+
+    #[test]
+    fn test_diversity_prefers_other_teams() {
+        let csv = "1,A,team=X\n2,B,team=X\n3,C,team=Y\n4,D,team=Y";
+        for solver in [Solver::Genetic, Solver::Exact, Solver::Heuristic] {
+            let people = People::from_csv(csv.as_bytes()).unwrap();
+            let config = PairingConfig::new()
+                .with_solver(solver)
+                .with_diversity("team", 1.0);
+            let pairs = pair(people, &History::default(), &config)
+                .unwrap()
+                .into_groups()
+                .inner();
+            for p in pairs {
+                assert!(!matches!(
+                    (p[0].id, p[1].id),
+                    (1, 2) | (2, 1) | (3, 4) | (4, 3)
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_mentoring() {
+        let csv =
+            "1,A,role=mentor\n2,B,role=mentor\n3,C,role=mentee\n4,D,role=mentee\n5,E,role=mentee";
+        let people = People::from_csv(csv.as_bytes()).unwrap();
+        let config = PairingConfig::new()
+            .with_mentoring(true)
+            .with_pins(pins(&[(1, 3)]));
+        let groups = pair(people, &History::default(), &config)
+            .unwrap()
+            .into_groups()
+            .inner();
+        assert_eq!(groups.len(), 3);
+        assert!(groups.iter().flatten().all(|p| p.id != usize::MAX));
+        assert_eq!(groups.iter().filter(|g| g.len() == 1).count(), 1);
+
+        let people = People::from_csv(csv.as_bytes()).unwrap();
+        let config = PairingConfig::new().with_mentoring(true).with_group_size(3);
+        let r = pair(people, &History::default(), &config);
+        assert!(matches!(r, Err(BuddyError::MentoringNeedsPairs(3))));
+    }
+
+    struct Apart(usize, usize);
+
+    impl PairScorer for Apart {
+        fn score(&self, _people: &People, a: usize, b: usize) -> f64 {
+            if (a, b) == (self.0, self.1) || (b, a) == (self.0, self.1) {
+                1.0
+            } else {
+                0.0
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_scorers() {
+        let csv = "1,A\n2,B\n3,C\n4,D";
+        let mut history = History::default();
+        // 1-4 and 2-3 met, which leaves 1-2 and 3-4, unless the scorers say otherwise
+        history::merge(&mut history, &[(1, 4), (2, 3)]);
+        for solver in [Solver::Genetic, Solver::Exact, Solver::Heuristic] {
+            let people = People::from_csv(csv.as_bytes()).unwrap();
+            let config = PairingConfig::new()
+                .with_solver(solver)
+                .with_scorer(Apart(1, 2), 2.0)
+                .with_scorer(Apart(3, 4), 0.5);
+            let pairs = pair(people, &history, &config)
+                .unwrap()
+                .into_groups()
+                .inner();
+            let ids: Vec<(usize, usize)> = pairs.iter().map(|p| (p[0].id, p[1].id)).collect();
+            assert!(ids.contains(&(1, 3)) || ids.contains(&(3, 1)));
+        }
+    }
+
+    #[test]
+    fn test_byes_rotate() {
+        let csv = "1,A\n2,B\n3,C\n4,D\n5,E";
+        for solver in [Solver::Genetic, Solver::Exact, Solver::Heuristic] {
+            let mut history = History::default();
+            // in five rounds, everyone sits out exactly once
+            for _ in 0..5 {
+                let people = People::from_csv(csv.as_bytes()).unwrap();
+                let config = PairingConfig::new().with_solver(solver);
+                let groups = pair(people, &history, &config)
+                    .unwrap()
+                    .into_groups()
+                    .inner();
+                let pairs: Vec<(usize, usize)> =
+                    groups.iter().map(|g| (g[0].id, g[1].id)).collect();
+                history::merge(&mut history, &pairs);
+            }
+            for id in 1..=5 {
+                assert_eq!(history.byes(id), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_odd_triad() {
+        let csv = "1,A\n2,B\n3,C\n4,D\n5,E";
+        let mut history = History::default();
+        // 1 met 2 and 3, which only leaves 1, 4 and 5 as the triad
+        history::merge(&mut history, &[(1, 2), (1, 3)]);
+        for solver in [Solver::Genetic, Solver::Exact, Solver::Heuristic] {
+            let people = People::from_csv(csv.as_bytes()).unwrap();
+            let config = PairingConfig::new()
+                .with_solver(solver)
+                .with_odd_policy(OddPolicy::Triad);
+            let groups = pair(people, &history, &config)
+                .unwrap()
+                .into_groups()
+                .inner();
+            let mut sizes: Vec<usize> = groups.iter().map(Vec::len).collect();
+            sizes.sort_unstable();
+            assert_eq!(sizes, vec![2, 3]);
+            assert!(groups.iter().flatten().all(|p| p.id != usize::MAX));
+            for g in groups {
+                for (n, a) in g.iter().enumerate() {
+                    for b in &g[n + 1..] {
+                        assert_eq!(history.get((a.id, b.id)), None);
                     }
                 }
-            };
-            // high score should be bad
-            score += last as isize;
+            }
+        }
+    }
+
+    #[test]
+    fn test_genetic_parameters() {
+        let csv = "1,A\n2,B\n3,C\n4,D";
+        let config = PairingConfig::new()
+            .with_population_size(10)
+            .with_max_stale_generations(5)
+            .with_mutation_rate(0.5)
+            .with_crossover_rate(0.5)
+            .with_elite_selection(0.3, 0.2);
+        let people = People::from_csv(csv.as_bytes()).unwrap();
+        let pairs = pair(people, &History::default(), &config)
+            .unwrap()
+            .into_groups()
+            .inner();
+        assert_eq!(pairs.len(), 2);
+
+        // the same, all at once
+        let params = PairingConfig::new().with_genetic_params(GeneticParams {
+            population_size: Some(10),
+            max_stale_generations: Some(5),
+            mutation_rate: Some(0.5),
+            crossover_rate: Some(0.5),
+            elite_selection: Some((0.3, 0.2)),
+            time_limit: None,
         });
-        trace!("Score for chromosome {:?}: {score}", chromosome.genes());
-        Some(score)
+        assert_eq!(params.population_size(), config.population_size());
+        assert_eq!(params.max_stale_generations(), 5);
+        assert!((params.mutation_rate() - 0.5).abs() < f32::EPSILON);
+        assert!((params.crossover_rate() - 0.5).abs() < f32::EPSILON);
+        assert_eq!(params.elite_selection(), (0.3, 0.2));
+        assert_eq!(params.time_limit(), None);
+        // nothing filled in changes nothing
+        let defaults = PairingConfig::new().with_genetic_params(GeneticParams::default());
+        assert_eq!(defaults.population_size(), 50);
+        assert_eq!(defaults.elite_selection(), (0.5, 0.5));
+
+        for config in [
+            PairingConfig::new().with_population_size(1),
+            PairingConfig::new().with_mutation_rate(1.5),
+            PairingConfig::new().with_elite_selection(0.5, -0.1),
+        ] {
+            let people = People::from_csv(csv.as_bytes()).unwrap();
+            let r = pair(people, &History::default(), &config);
+            assert!(matches!(r, Err(BuddyError::InvalidSolverParameter(_))));
+        }
+    }
+
+    #[test]
+    fn test_half_life_is_repeatable() {
+        use chrono::{NaiveDateTime, TimeDelta};
+        use history::merge_at;
+
+        let start = NaiveDateTime::parse_from_str("20240101_090000", "%Y%m%d_%H%M%S").unwrap();
+        let mut history = History::default();
+        let rounds = [[(1, 2), (3, 4)], [(1, 3), (2, 4)], [(1, 4), (2, 3)]];
+        for (days, round) in [0, 30, 60].into_iter().zip(&rounds) {
+            merge_at(&mut history, round, Some(start + TimeDelta::days(days)));
+        }
+        let config = PairingConfig::new()
+            .with_decay(Decay::HalfLife(Duration::from_secs(30 * 24 * 60 * 60)))
+            .with_seed(7);
+        let run = || {
+            pair(
+                People::from_csv("1,A\n2,B\n3,C\n4,D".as_bytes()).unwrap(),
+                &history,
+                &config,
+            )
+            .unwrap()
+        };
+        let (first, second) = (run(), run());
+        assert_eq!(
+            first.groups().clone().inner(),
+            second.groups().clone().inner()
+        );
+        // two half-lives before the newest round, whenever the test runs
+        assert_eq!(first.score(), 500);
+        assert_eq!(first.score(), second.score());
+    }
+
+    #[test]
+    fn test_pair_outcome() {
+        let csv = "1,A\n2,B\n3,C\n4,D";
+        let mut history = History::default();
+        // every possible pairing repeats one of these
+        history::merge(&mut history, &[(1, 2), (3, 4)]);
+        history::merge(&mut history, &[(1, 3), (2, 4)]);
+        history::merge(&mut history, &[(1, 4)]);
+        for solver in [Solver::Genetic, Solver::Exact, Solver::Heuristic] {
+            let people = People::from_csv(csv.as_bytes()).unwrap();
+            let config = PairingConfig::new().with_solver(solver);
+            let outcome = pair(people, &history, &config).unwrap();
+            assert_eq!(outcome.repeats(), 1);
+            assert_eq!(outcome.score(), 1000);
+            assert_eq!(outcome.previous_meetings().len(), 2);
+            assert_eq!(outcome.generations().is_some(), solver == Solver::Genetic);
+        }
+    }
+
+    #[test]
+    fn test_absent() {
+        let csv = "1,A\n2,B\n3,C\n4,D";
+        let people = People::from_csv(csv.as_bytes()).unwrap();
+        let config = PairingConfig::new().with_absent([2]);
+        let groups = pair(people, &History::default(), &config)
+            .unwrap()
+            .into_groups()
+            .inner();
+        // three left, so someone sits out with the evenizer
+        assert_eq!(groups.len(), 2);
+        assert!(groups.iter().flatten().any(|p| p.id == usize::MAX));
+        assert!(groups.iter().flatten().all(|p| p.id != 2));
+
+        let people = People::from_csv(csv.as_bytes()).unwrap();
+        let config = PairingConfig::new()
+            .with_absent([2])
+            .with_pins(pins(&[(1, 2)]));
+        let r = pair(people, &History::default(), &config);
+        assert!(matches!(r, Err(BuddyError::PinnedAbsent(2))));
+    }
+
+    #[test]
+    fn test_too_few_people() {
+        for solver in [Solver::Genetic, Solver::Exact, Solver::Heuristic] {
+            let config = PairingConfig::new().with_solver(solver);
+            let people = People::from_csv("".as_bytes()).unwrap();
+            let r = pair(people, &History::default(), &config);
+            assert!(matches!(r, Err(BuddyError::EmptyRoster)));
+
+            let people = People::from_csv("1,A".as_bytes()).unwrap();
+            let r = pair(people, &History::default(), &config);
+            assert!(matches!(r, Err(BuddyError::TooFewPeople(1))));
+
+            let people = People::from_csv("1,A\n2,B".as_bytes()).unwrap();
+            let r = pair(
+                people,
+                &History::default(),
+                &config.clone().with_absent([1]),
+            );
+            assert!(matches!(r, Err(BuddyError::TooFewPeople(1))));
+        }
+        let people = People::from_csv("".as_bytes()).unwrap();
+        let r = plan_ahead(people, &History::default(), &PairingConfig::new(), 2);
+        assert!(matches!(r, Err(BuddyError::EmptyRoster)));
+    }
+
+    #[test]
+    fn test_solver_failure() {
+        let people = People::from_csv("1,A\n2,B".as_bytes()).unwrap();
+        let config = PairingConfig::new();
+        let costs = Costs::new(&people, &History::default(), &config);
+        let r = solve_genetic(&[], &[], &costs, &config, 0, &Watch::new(&config));
+        assert!(matches!(r, Err(BuddyError::SolverFailure(_))));
+        let r = to_people(&people, &[vec![1, 7]]);
+        assert!(matches!(r, Err(BuddyError::SolverFailure(_))));
+    }
+
+    /// Everyone already met everyone, so the genetic algorithm never finds a perfect score and
+    /// keeps going.
+    fn all_met(n: usize) -> (People, History) {
+        let people = test_util::people(n);
+        let pairs: Vec<(usize, usize)> = (1..=n)
+            .flat_map(|i| (i + 1..=n).map(move |j| (i, j)))
+            .collect();
+        let mut history = History::default();
+        history::merge(&mut history, &pairs);
+        (people, history)
+    }
+
+    #[test]
+    fn test_progress() {
+        let (people, history) = all_met(10);
+        let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = Arc::clone(&reports);
+        let config = PairingConfig::new()
+            .with_seed(1)
+            .with_max_stale_generations(20)
+            .with_progress(move |progress| seen.lock().unwrap().push(progress));
+        let outcome = pair(people, &history, &config).unwrap();
+
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), outcome.generations().unwrap());
+        assert!(
+            reports
+                .windows(2)
+                .all(|w| w[0].generation < w[1].generation)
+        );
+        assert_eq!(reports.last().unwrap().best_score, Some(outcome.score()));
+    }
+
+    #[test]
+    fn test_time_limit_and_cancel() {
+        let (people, history) = all_met(30);
+
+        // out of time right away, but still some groups
+        let config = PairingConfig::new()
+            .with_max_stale_generations(usize::MAX)
+            .with_time_limit(Duration::ZERO);
+        let outcome = pair(people.clone(), &history, &config).unwrap();
+        assert_eq!(outcome.groups().clone().inner().len(), 15);
+        assert_eq!(outcome.generations(), Some(1));
+
+        let cancel = CancelToken::new();
+        let config = PairingConfig::new()
+            .with_max_stale_generations(usize::MAX)
+            .with_cancel(cancel.clone());
+        let handle = std::thread::spawn(move || pair(people, &history, &config));
+        cancel.cancel();
+        let r = handle.join().unwrap();
+        assert!(matches!(r, Err(BuddyError::Cancelled)));
+    }
+
+    #[test]
+    fn test_time_limit_keeps_the_rules() {
+        // everyone but 31 sat out before, so only 31 may take the bye
+        let people = test_util::people(31);
+        let mut history = History::default();
+        for id in 1..=30 {
+            history::merge(&mut history, &[(id, usize::MAX)]);
+        }
+        for limit in [Duration::ZERO, Duration::from_millis(5)] {
+            let config = PairingConfig::new()
+                .with_max_stale_generations(usize::MAX)
+                .with_time_limit(limit)
+                .with_seed(4);
+            let groups = pair(people.clone(), &history, &config)
+                .unwrap()
+                .into_groups()
+                .inner();
+            let bye = groups
+                .iter()
+                .find(|g| g.iter().any(|p| p.id == usize::MAX))
+                .unwrap();
+            assert!(bye.iter().any(|p| p.id == 31), "{bye:?} took the bye");
+        }
+
+        // bigger groups have nothing to fall back on
+        let mut exclusions = Exclusions::new();
+        for id in 2..=31 {
+            exclusions.insert(1, id);
+        }
+        let config = PairingConfig::new()
+            .with_group_size(3)
+            .with_exclusions(exclusions)
+            .with_time_limit(Duration::ZERO);
+        let r = pair(people, &History::default(), &config);
+        assert!(matches!(r, Err(BuddyError::OutOfTime)));
+    }
+
+    #[test]
+    fn test_pair_alternatives() {
+        let people = || People::from_csv("1,A\n2,B\n3,C\n4,D\n5,E\n6,F".as_bytes()).unwrap();
+        let mut history = History::default();
+        history::merge(&mut history, &[(1, 2), (3, 4), (5, 6)]);
+        for solver in [Solver::Genetic, Solver::Exact, Solver::Heuristic] {
+            let config = PairingConfig::new().with_solver(solver).with_seed(3);
+            let best = pair(people(), &history, &config).unwrap();
+            let outcomes = pair_alternatives(people(), &history, &config, 4).unwrap();
+            assert!(outcomes.len() > 1, "{solver:?} found no alternatives");
+            assert!(outcomes.len() <= 4);
+            assert_eq!(
+                outcomes[0].groups().clone().inner(),
+                best.into_groups().inner()
+            );
+            assert!(outcomes.windows(2).all(|w| w[0].score() <= w[1].score()));
+
+            let mut seen = HashSet::new();
+            for outcome in &outcomes {
+                let mut key: Vec<Vec<usize>> = outcome
+                    .groups()
+                    .clone()
+                    .inner()
+                    .iter()
+                    .map(|g| {
+                        let mut ids: Vec<usize> = g.iter().map(|p| p.id).collect();
+                        ids.sort_unstable();
+                        ids
+                    })
+                    .collect();
+                key.sort_unstable();
+                assert!(seen.insert(key), "{solver:?} repeated an alternative");
+            }
+        }
+
+        // four people can only be paired three ways
+        let people = People::from_csv("1,A\n2,B\n3,C\n4,D".as_bytes()).unwrap();
+        let config = PairingConfig::new().with_solver(Solver::Exact);
+        let outcomes = pair_alternatives(people, &History::default(), &config, 10).unwrap();
+        assert_eq!(outcomes.len(), 3);
+    }
+
+    #[test]
+    fn test_cooldown() {
+        let people = || People::from_csv("1,A\n2,B\n3,C\n4,D".as_bytes()).unwrap();
+        let mut history = History::default();
+        history::merge(&mut history, &[(1, 2), (3, 4)]);
+        history::merge(&mut history, &[(1, 3), (2, 4)]);
+        for solver in [Solver::Genetic, Solver::Exact, Solver::Heuristic] {
+            let config = PairingConfig::new().with_solver(solver).with_cooldown(2);
+            let outcome = pair(people(), &history, &config).unwrap();
+            assert_eq!(outcome.repeats(), 0);
+        }
+
+        history::merge(&mut history, &[(1, 4), (2, 3)]);
+        for solver in [Solver::Genetic, Solver::Exact, Solver::Heuristic] {
+            let config = PairingConfig::new().with_solver(solver).with_cooldown(3);
+            let r = pair(people(), &history, &config);
+            assert!(matches!(r, Err(BuddyError::UnsatisfiableConstraints)));
+        }
+    }
+
+    #[test]
+    fn test_preferences() {
+        let preferences =
+            Preferences::from_csv("1,3,1\n3,1,1\n2,4,0.5\n1,2,-1".as_bytes()).unwrap();
+        for solver in [Solver::Genetic, Solver::Exact, Solver::Heuristic] {
+            let config = PairingConfig::new()
+                .with_solver(solver)
+                .with_seed(7)
+                .with_preferences(preferences.clone(), 0.4);
+            let people = People::from_csv("1,A\n2,B\n3,C\n4,D".as_bytes()).unwrap();
+            let pairs = pair(people, &History::default(), &config)
+                .unwrap()
+                .into_groups()
+                .inner();
+            let mut ids: Vec<(usize, usize)> = pairs
+                .iter()
+                .map(|p| (p[0].id.min(p[1].id), p[0].id.max(p[1].id)))
+                .collect();
+            ids.sort_unstable();
+            assert_eq!(ids, vec![(1, 3), (2, 4)]);
+        }
+
+        // but a repeat still counts for more
+        let mut history = History::default();
+        history::merge(&mut history, &[(1, 3), (2, 4)]);
+        let config = PairingConfig::new()
+            .with_solver(Solver::Exact)
+            .with_preferences(preferences, 0.4);
+        let people = People::from_csv("1,A\n2,B\n3,C\n4,D".as_bytes()).unwrap();
+        let outcome = pair(people, &history, &config).unwrap();
+        assert_eq!(outcome.repeats(), 0);
+    }
+
+    #[test]
+    fn test_min_overlap() {
+        // 1 and 2 are in Europe, 3 and 4 on the US west coast
+        let csv = "1,A,timezone=+1\n2,B,timezone=+2\n3,C,timezone=-8\n4,D,timezone=-7";
+        for solver in [Solver::Genetic, Solver::Exact, Solver::Heuristic] {
+            let people = People::from_csv(csv.as_bytes()).unwrap();
+            let config = PairingConfig::new()
+                .with_solver(solver)
+                .with_min_overlap(Duration::from_secs(60 * 60));
+            let pairs = pair(people, &History::default(), &config)
+                .unwrap()
+                .into_groups()
+                .inner();
+            for p in pairs {
+                assert!(matches!(
+                    (p[0].id.min(p[1].id), p[0].id.max(p[1].id)),
+                    (1, 2) | (3, 4)
+                ));
+            }
+        }
+
+        let people = People::from_csv("1,A,timezone=+1,hours=late\n2,B".as_bytes()).unwrap();
+        let config = PairingConfig::new().with_min_overlap(Duration::from_secs(60 * 60));
+        let r = pair(people, &History::default(), &config);
+        assert!(matches!(r, Err(BuddyError::TimeZoneFormatError(1))));
+    }
+
+    #[test]
+    fn test_pair_invalid_group_size() {
+        let people = People::from_csv("1,A\n2,B".as_bytes()).unwrap();
+        let config = PairingConfig::new().with_group_size(1);
+        let r = pair(people, &History::default(), &config);
+        assert!(matches!(r, Err(BuddyError::InvalidGroupSize(1))));
     }
 }
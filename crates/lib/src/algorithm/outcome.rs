@@ -0,0 +1,125 @@
+use super::Groups;
+use super::cost::Costs;
+use crate::History;
+use std::fmt;
+use std::time::Duration;
+
+/// What [`pair`](super::pair) came up with, and how good it is.
+#[derive(Debug, Clone)]
+pub struct PairingOutcome {
+    groups: Groups,
+    score: isize,
+    previous: Vec<((usize, usize), usize)>,
+    generations: Option<usize>,
+    elapsed: Duration,
+}
+
+impl PairingOutcome {
+    pub(crate) fn new(
+        groups: Groups,
+        last: &History,
+        costs: &Costs,
+        generations: Option<usize>,
+        elapsed: Duration,
+    ) -> Self {
+        let mut score = 0;
+        let mut previous = Vec::new();
+        for group in &groups.groups {
+            let ids: Vec<usize> = group.iter().map(|p| p.id).collect();
+            score += costs.group(&ids);
+            for (n, &i) in ids.iter().enumerate() {
+                for &j in &ids[n + 1..] {
+                    // sitting out isn't meeting anyone
+                    if i != usize::MAX && j != usize::MAX {
+                        previous.push(((i, j), last.get((i, j)).unwrap_or(0)));
+                    }
+                }
+            }
+        }
+        Self {
+            groups,
+            score,
+            previous,
+            generations,
+            elapsed,
+        }
+    }
+
+    pub fn groups(&self) -> &Groups {
+        &self.groups
+    }
+
+    pub fn into_groups(self) -> Groups {
+        self.groups
+    }
+
+    /// The total cost of the groups, as the solver sees it. Lower is better, and 0 means no
+    /// repeats and nothing else to complain about.
+    pub fn score(&self) -> isize {
+        self.score
+    }
+
+    /// How many of the pairs met before.
+    pub fn repeats(&self) -> usize {
+        self.previous.iter().filter(|(_, met)| *met > 0).count()
+    }
+
+    /// Every pair that meets in these groups, and how often they met before.
+    pub fn previous_meetings(&self) -> &[((usize, usize), usize)] {
+        &self.previous
+    }
+
+    /// How many generations the genetic algorithm ran, if it was used.
+    pub fn generations(&self) -> Option<usize> {
+        self.generations
+    }
+
+    /// How long finding the groups took.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+impl fmt::Display for PairingOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} pairs met before, score {}",
+            self.repeats(),
+            self.previous.len(),
+            self.score
+        )?;
+        if let Some(generations) = self.generations {
+            write!(f, ", {generations} generations")?;
+        }
+        write!(f, ", took {:.2?}", self.elapsed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::algorithm::history::merge;
+    use crate::{PairingConfig, People, Person};
+
+    #[test]
+    fn test_outcome() {
+        let people = People::from_csv("1,A\n2,B\n3,C".as_bytes()).unwrap();
+        let mut history = History::default();
+        merge(&mut history, &[(1, 2), (1, 2)]);
+        let costs = Costs::new(&people, &history, &PairingConfig::new());
+        let person = |id: usize| Person::new(id, id.to_string());
+        let groups = Groups::new(vec![
+            vec![person(1), person(2)],
+            vec![person(3), person(usize::MAX)],
+        ]);
+        let outcome = PairingOutcome::new(groups, &history, &costs, None, Duration::ZERO);
+        assert_eq!(outcome.repeats(), 1);
+        assert_eq!(outcome.previous_meetings(), &[((1, 2), 2)]);
+        assert_eq!(outcome.score(), 2000);
+        assert_eq!(
+            outcome.to_string(),
+            "1 of 1 pairs met before, score 2000, took 0.00ns"
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use crate::BuddyError;
+use crate::People;
+use std::collections::HashMap;
+
+/// The attribute holding someone's offset from UTC, like `+2`, `-5` or `UTC+5:30`.
+pub(crate) const TIMEZONE: &str = "timezone";
+/// The attribute holding someone's local working hours, like `9-17` or `8:30-16:30`.
+pub(crate) const HOURS: &str = "hours";
+
+const DAY: i32 = 24 * 60;
+/// Working hours for anyone with a time zone, but no `hours` attribute.
+const DEFAULT_HOURS: (i32, i32) = (9 * 60, 17 * 60);
+
+/// When someone works, in minutes after midnight UTC. The end can go past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct WorkingHours {
+    start: i32,
+    end: i32,
+}
+
+impl WorkingHours {
+    /// How many minutes two people's working hours overlap, going around the clock.
+    pub(crate) fn overlap(self, other: WorkingHours) -> i32 {
+        [-DAY, 0, DAY]
+            .iter()
+            .map(|shift| {
+                let start = self.start.max(other.start + shift);
+                let end = self.end.min(other.end + shift);
+                (end - start).max(0)
+            })
+            .sum()
+    }
+}
+
+/// Everyone's working hours, for those with a `timezone` attribute.
+pub(crate) fn working_hours(people: &People) -> Result<HashMap<usize, WorkingHours>, BuddyError> {
+    let mut hours = HashMap::new();
+    for id in people.as_ids() {
+        let Some(timezone) = people.attribute(id, TIMEZONE) else {
+            continue;
+        };
+        let offset = parse_offset(timezone).ok_or(BuddyError::TimeZoneFormatError(id))?;
+        let (start, end) = match people.attribute(id, HOURS) {
+            Some(local) => parse_hours(local).ok_or(BuddyError::TimeZoneFormatError(id))?,
+            None => DEFAULT_HOURS,
+        };
+        // `0-24` or `9-9` is around the clock, not no time at all
+        let length = match (end - start).rem_euclid(DAY) {
+            0 => DAY,
+            length => length,
+        };
+        let start = (start - offset).rem_euclid(DAY);
+        hours.insert(
+            id,
+            WorkingHours {
+                start,
+                end: start + length,
+            },
+        );
+    }
+    Ok(hours)
+}
+
+/// Parses a UTC offset like `+2`, `-5`, `+5:30` or `UTC-3` into minutes.
+fn parse_offset(offset: &str) -> Option<i32> {
+    let offset = offset
+        .trim()
+        .trim_start_matches("UTC")
+        .trim_start_matches("GMT");
+    if offset.is_empty() {
+        return Some(0);
+    }
+    let (sign, time) = if let Some(time) = offset.strip_prefix('+') {
+        (1, time)
+    } else if let Some(time) = offset.strip_prefix('-') {
+        (-1, time)
+    } else {
+        (1, offset)
+    };
+    let minutes = parse_time(time)?;
+    (minutes <= 14 * 60).then_some(sign * minutes)
+}
+
+/// Parses local working hours like `9-17` or `8:30-16:30` into minutes after midnight.
+fn parse_hours(hours: &str) -> Option<(i32, i32)> {
+    let (start, end) = hours.split_once('-')?;
+    Some((parse_time(start)?, parse_time(end)?))
+}
+
+/// Parses `9` or `9:30` into minutes.
+fn parse_time(time: &str) -> Option<i32> {
+    let (hours, minutes) = time.trim().split_once(':').unwrap_or((time.trim(), "0"));
+    let (hours, minutes): (i32, i32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    ((0..=24).contains(&hours) && (0..60).contains(&minutes)).then_some(hours * 60 + minutes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse_offset("+2"), Some(120));
+        assert_eq!(parse_offset("UTC-5:30"), Some(-330));
+        assert_eq!(parse_offset("UTC"), Some(0));
+        assert_eq!(parse_offset("Berlin"), None);
+        assert_eq!(parse_hours("8:30-16"), Some((510, 960)));
+        assert_eq!(parse_hours("9"), None);
+    }
+
+    #[test]
+    fn test_overlap() {
+        let csv =
+            "1,A,timezone=+0\n2,B,timezone=+2\n3,C,timezone=-8\n4,D,timezone=+9,hours=22-6\n5,E";
+        let people = People::from_csv(csv.as_bytes()).unwrap();
+        let hours = working_hours(&people).unwrap();
+        assert_eq!(hours.len(), 4);
+        assert_eq!(hours[&1].overlap(hours[&2]), 6 * 60);
+        assert_eq!(hours[&1].overlap(hours[&3]), 0);
+        // 22-6 in +9 is 13-21 UTC, past midnight locally but not in UTC
+        assert_eq!(hours[&4].overlap(hours[&1]), 4 * 60);
+        assert_eq!(hours[&4].overlap(hours[&3]), 4 * 60);
+
+        let people = People::from_csv("1,A,timezone=Mars".as_bytes()).unwrap();
+        assert!(matches!(
+            working_hours(&people),
+            Err(BuddyError::TimeZoneFormatError(1))
+        ));
+    }
+
+    #[test]
+    fn test_around_the_clock() {
+        let csv = "1,A,timezone=+0,hours=0-24\n2,B,timezone=+3,hours=9-9\n3,C,timezone=-8";
+        let people = People::from_csv(csv.as_bytes()).unwrap();
+        let hours = working_hours(&people).unwrap();
+        assert_eq!(hours[&1].overlap(hours[&3]), 8 * 60);
+        assert_eq!(hours[&2].overlap(hours[&3]), 8 * 60);
+        assert_eq!(hours[&1].overlap(hours[&2]), DAY);
+    }
+}
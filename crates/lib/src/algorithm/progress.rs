@@ -0,0 +1,142 @@
+use super::PairingConfig;
+use genetic_algorithm::strategy::evolve::prelude::*;
+use rand::Rng;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// How far the genetic algorithm got, handed to the callback from
+/// [`with_progress`](super::PairingConfig::with_progress) after every generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// How many generations ran so far, or rounds of swaps for the
+    /// [`Heuristic`](super::Solver::Heuristic) solver.
+    pub generation: usize,
+    /// The best score found so far, like [`PairingOutcome::score`](super::PairingOutcome::score).
+    pub best_score: Option<isize>,
+    /// How long the search has been running.
+    pub elapsed: Duration,
+}
+
+/// Stops a [`pair`](super::pair) or [`plan_ahead`](super::plan_ahead) running on another thread.
+/// Clone it, hand one to the [`PairingConfig`] with
+/// [`with_cancel`](super::PairingConfig::with_cancel), and call [`cancel`](Self::cancel) on the
+/// other.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A progress callback that can live in the [`PairingConfig`].
+#[derive(Clone)]
+pub(crate) struct ProgressFn(pub(crate) Arc<dyn Fn(Progress) + Send + Sync>);
+
+impl fmt::Debug for ProgressFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressFn").finish_non_exhaustive()
+    }
+}
+
+/// Keeps an eye on a search: reports progress, and ends it once the time is up or it got
+/// cancelled.
+#[derive(Clone, Debug)]
+pub(crate) struct Watch<G> {
+    start: Instant,
+    deadline: Option<Instant>,
+    progress: Option<ProgressFn>,
+    cancel: Option<CancelToken>,
+    genotype: PhantomData<fn() -> G>,
+}
+
+impl<G> Watch<G> {
+    pub(crate) fn new(config: &PairingConfig) -> Self {
+        let start = Instant::now();
+        Self {
+            start,
+            deadline: config.time_limit.map(|limit| start + limit),
+            progress: config.progress.clone(),
+            cancel: config.cancel.clone(),
+            genotype: PhantomData,
+        }
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+
+    /// Hands how far the search got to the progress callback, if there is one.
+    pub(crate) fn report(&self, generation: usize, best_score: Option<isize>) {
+        if let Some(progress) = &self.progress {
+            (progress.0)(Progress {
+                generation,
+                best_score,
+                elapsed: self.start.elapsed(),
+            });
+        }
+    }
+
+    /// Whether the search should wrap up, because it got cancelled or ran out of time.
+    pub(crate) fn should_stop(&self) -> bool {
+        self.is_cancelled()
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+impl<G: EvolveGenotype> Extension for Watch<G> {
+    type Genotype = G;
+
+    fn after_generation_complete<R: Rng, SR: StrategyReporter<Genotype = Self::Genotype>>(
+        &mut self,
+        _genotype: &mut Self::Genotype,
+        state: &mut EvolveState<Self::Genotype>,
+        _config: &EvolveConfig,
+        _reporter: &mut SR,
+        _rng: &mut R,
+    ) {
+        self.report(state.current_generation, state.best_fitness_score);
+        // as good as giving up on finding anything better
+        if self.should_stop() {
+            state.stale_generations = usize::MAX;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cancel_token() {
+        let token = CancelToken::new();
+        let watch = Watch::<()>::new(&PairingConfig::new().with_cancel(token.clone()));
+        assert!(!watch.should_stop());
+        token.cancel();
+        assert!(watch.is_cancelled());
+        assert!(watch.should_stop());
+    }
+
+    #[test]
+    fn test_time_limit() {
+        let watch = Watch::<()>::new(&PairingConfig::new().with_time_limit(Duration::ZERO));
+        assert!(!watch.is_cancelled());
+        assert!(watch.should_stop());
+        let watch = Watch::<()>::new(&PairingConfig::new());
+        assert!(!watch.should_stop());
+    }
+}
@@ -0,0 +1,283 @@
+use super::cost::Costs;
+use super::history::pairs_in_group;
+use super::{Groups, PairingConfig, PairingOutcome, pair_rejecting};
+use crate::BuddyError;
+use crate::History;
+use crate::People;
+use crate::Pins;
+use std::collections::BTreeSet;
+use std::time::Instant;
+use tracing::debug;
+
+/// Re-does part of a round. Every group in `previous` that has one of the `keep` pairs in it stays
+/// as it is, and everyone else is paired again, like [`pair`](super::pair) would with just them.
+/// The [`PairingOutcome`] has the kept groups first, then the new ones.
+///
+/// The `last` history shouldn't have `previous` in it yet. Since those groups didn't work out, the
+/// released people only get the same partner back when there's no one else left. They didn't
+/// really meet, though, so the cooldown and the decay only go by `last`. Pins and absences in the
+/// [`PairingConfig`] are ignored; the round decides who takes part.
+pub fn reroll(
+    people: People,
+    last: &History,
+    config: &PairingConfig,
+    previous: &Groups,
+    keep: &[(usize, usize)],
+) -> Result<PairingOutcome, BuddyError> {
+    let keep = keep
+        .iter()
+        .map(|&(a, b)| {
+            previous
+                .groups
+                .iter()
+                .position(|g| g.iter().any(|p| p.id == a) && g.iter().any(|p| p.id == b))
+                .ok_or(BuddyError::NotInRound(a, b))
+        })
+        .collect::<Result<BTreeSet<_>, _>>()?;
+    reroll_groups(people, last, config, previous, &keep)
+}
+
+/// Like [`reroll`], but keeps the groups at the positions in `keep`, counting from 0. Handy when
+/// whoever picks them sees the whole groups, not just two people in each.
+pub fn reroll_groups(
+    people: People,
+    last: &History,
+    config: &PairingConfig,
+    previous: &Groups,
+    keep: &BTreeSet<usize>,
+) -> Result<PairingOutcome, BuddyError> {
+    let start = Instant::now();
+    let in_round: BTreeSet<usize> = previous
+        .groups
+        .iter()
+        .flatten()
+        .map(|p| p.id)
+        .filter(|id| *id != usize::MAX)
+        .collect();
+    if let Some(id) = in_round.iter().find(|id| !people.contains(**id)) {
+        return Err(BuddyError::UnknownRerollPerson(*id));
+    }
+    if let Some(&n) = keep.range(previous.groups.len()..).next() {
+        return Err(BuddyError::NoSuchGroup(n, previous.groups.len()));
+    }
+
+    let kept: Vec<bool> = (0..previous.groups.len())
+        .map(|n| keep.contains(&n))
+        .collect();
+    let (kept, released): (Vec<_>, Vec<_>) = previous
+        .groups
+        .iter()
+        .zip(kept)
+        .partition(|(_, kept)| *kept);
+    let kept: Vec<_> = kept.into_iter().map(|(g, _)| g.clone()).collect();
+    let released: Vec<_> = released.into_iter().map(|(g, _)| g).collect();
+
+    // the groups that didn't work out, minus anyone sitting out
+    let rejected: Vec<_> = released
+        .iter()
+        .flat_map(|g| pairs_in_group(g))
+        .filter(|(a, b)| *a != usize::MAX && *b != usize::MAX)
+        .collect();
+
+    let ids: BTreeSet<usize> = released
+        .iter()
+        .flat_map(|g| g.iter())
+        .map(|p| p.id)
+        .filter(|id| *id != usize::MAX)
+        .collect();
+    debug!(
+        "Re-rolling {} people, keeping {} groups",
+        ids.len(),
+        kept.len()
+    );
+    let others = |keep: &BTreeSet<usize>| {
+        people
+            .iter()
+            .map(|(id, _)| id)
+            .filter(|id| !keep.contains(id))
+            .collect::<BTreeSet<_>>()
+    };
+
+    let mut config = config.clone().with_pins(Pins::new());
+    config.absent.clear();
+    let (groups, seed, generations) = if ids.len() < 2 {
+        // nobody left to pair them with, so they sit out
+        let alone = released
+            .iter()
+            .flat_map(|g| g.iter())
+            .filter(|p| p.id != usize::MAX)
+            .map(|p| vec![p.clone()])
+            .collect();
+        (alone, None, None)
+    } else {
+        let outcome =
+            pair_rejecting(people.without(&others(&ids))?, last, &config, 1, &rejected)?.remove(0);
+        let generations = outcome.generations();
+        let groups = outcome.into_groups();
+        (groups.groups, groups.seed, generations)
+    };
+
+    // score the whole round against the real history
+    let round = people.without(&others(&in_round))?;
+    let costs = Costs::new(&round, last, &config);
+    let groups = Groups {
+        groups: kept.into_iter().chain(groups).collect(),
+        seed,
+    };
+    Ok(PairingOutcome::new(
+        groups,
+        last,
+        &costs,
+        generations,
+        start.elapsed(),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Person;
+    use crate::Solver;
+    use crate::algorithm::history::merge;
+    use crate::algorithm::test_util::{ids, people};
+
+    fn round(pairs: &[(usize, usize)]) -> Groups {
+        Groups::new(
+            pairs
+                .iter()
+                .map(|&(a, b)| vec![Person::new(a, a.to_string()), Person::new(b, b.to_string())])
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_reroll_keeps_pairs() {
+        let previous = round(&[(1, 2), (3, 4), (5, 6), (7, 8)]);
+        for solver in [Solver::Genetic, Solver::Exact, Solver::Heuristic] {
+            let config = PairingConfig::new().with_solver(solver).with_seed(1);
+            let outcome = reroll(
+                people(8),
+                &History::default(),
+                &config,
+                &previous,
+                &[(2, 1), (7, 8)],
+            )
+            .unwrap();
+            let pairs = ids(outcome.groups());
+            assert_eq!(pairs.len(), 4);
+            assert_eq!(&pairs[..2], &[(1, 2), (7, 8)]);
+            // the released people found someone new
+            assert!(!pairs.contains(&(3, 4)), "{solver:?} paired 3 and 4 again");
+            assert!(!pairs.contains(&(5, 6)), "{solver:?} paired 5 and 6 again");
+            assert_eq!(outcome.repeats(), 0);
+        }
+    }
+
+    #[test]
+    fn test_reroll_everything_or_nothing() {
+        let previous = round(&[(1, 2), (3, 4), (5, 6), (7, 8)]);
+        let config = PairingConfig::new().with_solver(Solver::Exact);
+        let outcome = reroll(people(8), &History::default(), &config, &previous, &[]).unwrap();
+        let pairs = ids(outcome.groups());
+        assert_eq!(pairs.len(), 4);
+        assert!(pairs.iter().all(|p| !ids(&previous).contains(p)));
+
+        let all = ids(&previous);
+        let outcome = reroll(people(8), &History::default(), &config, &previous, &all).unwrap();
+        assert_eq!(ids(outcome.groups()), all);
+    }
+
+    #[test]
+    fn test_reroll_keeps_cooldown() {
+        let mut last = History::default();
+        merge(&mut last, &[(1, 2), (3, 4)]);
+        merge(&mut last, &[(1, 3), (2, 4)]);
+        let config = PairingConfig::new()
+            .with_solver(Solver::Exact)
+            .with_cooldown(1);
+        let previous = round(&[(1, 4), (2, 3)]);
+        let outcome = reroll(people(4), &last, &config, &previous, &[]).unwrap();
+        // the rejected round isn't a round, so (1 3)(2 4) are still cooling down
+        assert_eq!(ids(outcome.groups()), vec![(1, 2), (3, 4)]);
+    }
+
+    #[test]
+    fn test_reroll_invalid() {
+        let previous = round(&[(1, 2), (3, 4), (5, 6), (7, 8)]);
+        let config = PairingConfig::new();
+        let r = reroll(
+            people(8),
+            &History::default(),
+            &config,
+            &previous,
+            &[(1, 3)],
+        );
+        assert!(matches!(r, Err(BuddyError::NotInRound(1, 3))));
+        let previous = round(&[(1, 2), (3, 9)]);
+        let r = reroll(
+            people(8),
+            &History::default(),
+            &config,
+            &previous,
+            &[(1, 2)],
+        );
+        assert!(matches!(r, Err(BuddyError::UnknownRerollPerson(9))));
+        let previous = round(&[(1, 2), (3, 4)]);
+        let r = reroll_groups(
+            people(4),
+            &History::default(),
+            &config,
+            &previous,
+            &BTreeSet::from([2]),
+        );
+        assert!(matches!(r, Err(BuddyError::NoSuchGroup(2, 2))));
+    }
+
+    #[test]
+    fn test_reroll_keeps_triads() {
+        let previous = Groups::new(vec![
+            vec![Person::new(1, "1".into()), Person::new(2, "2".into())],
+            vec![
+                Person::new(3, "3".into()),
+                Person::new(4, "4".into()),
+                Person::new(5, "5".into()),
+            ],
+            vec![Person::new(6, "6".into()), Person::new(7, "7".into())],
+        ]);
+        let config = PairingConfig::new().with_solver(Solver::Exact);
+        let outcome = reroll_groups(
+            people(7),
+            &History::default(),
+            &config,
+            &previous,
+            &BTreeSet::from([1]),
+        )
+        .unwrap();
+        let groups = outcome.groups().clone().inner();
+        assert_eq!(groups[0], previous.clone().inner()[1]);
+        assert_eq!(groups.len(), 3);
+    }
+
+    #[test]
+    fn test_reroll_one_left() {
+        let people = People::from_csv("1,A\n2,B\n3,C".as_bytes()).unwrap();
+        let previous = Groups::new(vec![
+            vec![Person::new(1, "A".into()), Person::new(2, "B".into())],
+            vec![
+                Person::new(3, "C".into()),
+                Person::new(usize::MAX, String::new()),
+            ],
+        ]);
+        let outcome = reroll(
+            people,
+            &History::default(),
+            &PairingConfig::new(),
+            &previous,
+            &[(1, 2)],
+        )
+        .unwrap();
+        let groups = outcome.groups().clone().inner();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[1], vec![Person::new(3, "C".into())]);
+    }
+}
@@ -0,0 +1,136 @@
+use super::Groups;
+use super::Pairs;
+use super::{check_roster, to_people};
+use crate::BuddyError;
+use crate::History;
+use crate::People;
+use tracing::debug;
+
+/// Plans the next `rounds` rounds of [`Pairs`] up front, using the round robin circle method.
+///
+/// For `n` people (counting the evenizer, if there is one) the circle method yields `n - 1`
+/// rounds in which everyone meets everyone else exactly once, so the first `n - 1` rounds of the
+/// schedule never repeat a pair among themselves. The rounds are ordered by how often their pairs
+/// already met in the [`History`], so rounds with the fewest repeats come first. Since the
+/// construction is deterministic, a history written from an earlier plan puts the rounds that
+/// already happened at the end, and a new plan picks up where the old one left off.
+///
+/// Asking for more than `n - 1` rounds starts over with the same cycle, since repeats are
+/// unavoidable at that point anyway.
+///
+/// Fails with [`BuddyError::EmptyRoster`] or [`BuddyError::TooFewPeople`] if there's nobody to
+/// pair, and with [`BuddyError::SolverFailure`] rather than panicking if a round ever names
+/// someone who isn't in `people`, which would be a bug.
+pub fn plan_schedule(
+    people: People,
+    last: &History,
+    rounds: usize,
+) -> Result<Vec<Pairs>, BuddyError> {
+    check_roster(&people)?;
+    let mut ids = people.as_ids();
+    ids.sort_unstable();
+
+    let mut cycle = circle_rounds(&ids);
+    // stable sort, so rounds without repeats keep their circle order
+    cycle.sort_by_cached_key(|round| {
+        round
+            .iter()
+            .map(|&(i, j)| last.get((i, j)).unwrap_or(0))
+            .sum::<usize>()
+    });
+    debug!("Planned a cycle of {} unique rounds", cycle.len());
+
+    cycle
+        .iter()
+        .cycle()
+        .take(rounds)
+        .map(|round| {
+            let pairs: Vec<_> = round.iter().map(|&(i, j)| vec![i, j]).collect();
+            Ok(Groups::new(to_people(&people, &pairs)?))
+        })
+        .collect()
+}
+
+/// The circle method: keep the last person in place and rotate everyone else around them. Each
+/// rotation gives one round, and after `n - 1` rotations every pair has met exactly once.
+fn circle_rounds(ids: &[usize]) -> Vec<Vec<(usize, usize)>> {
+    let n = ids.len();
+    if n < 2 || !n.is_multiple_of(2) {
+        return Vec::new();
+    }
+    let (circle, fixed) = (&ids[..n - 1], ids[n - 1]);
+    let m = circle.len();
+    (0..m)
+        .map(|r| {
+            let mut round = vec![(circle[r], fixed)];
+            for k in 1..n / 2 {
+                round.push((circle[(r + k) % m], circle[(r + m - k) % m]));
+            }
+            round
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::algorithm::history::merge;
+    use crate::algorithm::test_util::{ids, people};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_circle_rounds_unique() {
+        let ids: Vec<usize> = (0..8).collect();
+        let rounds = circle_rounds(&ids);
+        assert_eq!(rounds.len(), 7);
+        let mut seen = HashSet::new();
+        for round in rounds {
+            let mut people = HashSet::new();
+            for (i, j) in round {
+                // everyone is in exactly one pair per round, and no pair repeats
+                assert!(people.insert(i) && people.insert(j));
+                assert!(seen.insert((i.min(j), i.max(j))));
+            }
+            assert_eq!(people.len(), 8);
+        }
+        assert_eq!(seen.len(), 28);
+    }
+
+    #[test]
+    fn test_plan_no_repeats_until_exhausted() {
+        let schedule = plan_schedule(people(7), &History::default(), 9).unwrap();
+        assert_eq!(schedule.len(), 9);
+        let mut seen = HashSet::new();
+        // 7 people plus the evenizer make 8, so the first 7 rounds are unique
+        for round in &schedule[..7] {
+            for pair in ids(round) {
+                assert!(seen.insert(pair));
+            }
+        }
+        // then it starts over
+        assert_eq!(ids(&schedule[7]), ids(&schedule[0]));
+    }
+
+    #[test]
+    fn test_plan_continues_from_history() {
+        let first = plan_schedule(people(6), &History::default(), 2).unwrap();
+        let mut history = History::default();
+        for round in &first {
+            merge(&mut history, &ids(round));
+        }
+        let next = plan_schedule(people(6), &history, 3).unwrap();
+        for round in &next {
+            for pair in ids(round) {
+                assert_eq!(history.get(pair), None);
+            }
+        }
+    }
+
+    #[test]
+    fn test_plan_too_few() {
+        let r = plan_schedule(People::default(), &History::default(), 3);
+        assert!(matches!(r, Err(BuddyError::EmptyRoster)));
+        let r = plan_schedule(people(1), &History::default(), 3);
+        assert!(matches!(r, Err(BuddyError::TooFewPeople(1))));
+    }
+}
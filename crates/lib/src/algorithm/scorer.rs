@@ -0,0 +1,47 @@
+use crate::People;
+use std::fmt;
+use std::sync::Arc;
+
+/// A custom cost for putting two people in the same group, on top of how often they already met.
+/// Implement it to add your own penalties, like people without a common language, and add it to
+/// the [`PairingConfig`](super::PairingConfig) with
+/// [`with_scorer`](super::PairingConfig::with_scorer).
+///
+/// ```
+/// use buddy_up_lib::{PairScorer, People};
+///
+/// /// Prefers people who speak the same language.
+/// struct Language;
+///
+/// impl PairScorer for Language {
+///     fn score(&self, people: &People, a: usize, b: usize) -> f64 {
+///         let a = people.attribute(a, "language");
+///         if a.is_some() && a == people.attribute(b, "language") {
+///             0.0
+///         } else {
+///             1.0
+///         }
+///     }
+/// }
+/// ```
+pub trait PairScorer: Send + Sync {
+    /// How bad it is to put `a` and `b` together, in the same unit as the
+    /// [`History`](crate::History): 1.0 is as bad as them having met once before. Lower is better,
+    /// and negative scores count as zero. Use `people` to look up attributes from the input.
+    fn score(&self, people: &People, a: usize, b: usize) -> f64;
+}
+
+/// A [`PairScorer`] and how much it counts.
+#[derive(Clone)]
+pub(crate) struct WeightedScorer {
+    pub(crate) scorer: Arc<dyn PairScorer>,
+    pub(crate) weight: f64,
+}
+
+impl fmt::Debug for WeightedScorer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WeightedScorer")
+            .field("weight", &self.weight)
+            .finish_non_exhaustive()
+    }
+}
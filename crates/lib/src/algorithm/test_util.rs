@@ -0,0 +1,20 @@
+//! Helpers shared by the tests of the algorithm modules.
+
+use super::Groups;
+use crate::People;
+
+/// A roster of `n` people, with IDs 1 to `n` and names `P1` to `Pn`.
+pub(crate) fn people(n: usize) -> People {
+    let csv: Vec<String> = (1..=n).map(|i| format!("{i},P{i}")).collect();
+    People::from_csv(csv.join("\n").as_bytes()).unwrap()
+}
+
+/// The IDs in every pair of a round, smaller ID first.
+pub(crate) fn ids(groups: &Groups) -> Vec<(usize, usize)> {
+    groups
+        .clone()
+        .inner()
+        .iter()
+        .map(|g| (g[0].id.min(g[1].id), g[0].id.max(g[1].id)))
+        .collect()
+}
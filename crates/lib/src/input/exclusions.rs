@@ -0,0 +1,86 @@
+use super::read_id_pairs;
+use crate::BuddyError;
+use std::collections::HashSet;
+use std::io::Read;
+
+/// Pairs of people that must never end up in the same group, like a manager and their direct
+/// report. Give it a impl [`Read`], like a file, to get [`Exclusions`] back.
+///
+/// Example:
+/// ```ignore
+/// # use std::fs::File;
+/// # use buddy_up_lib::Exclusions;
+/// let f = File::open("exclusions.csv")?;
+/// let exclusions = Exclusions::from_csv(f)?;
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Exclusions {
+    pairs: HashSet<(usize, usize)>,
+}
+
+impl Exclusions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads exclusions from a CSV file. The expected format is rows of two IDs, `id,id`.
+    ///
+    /// Example CSV:
+    /// ```text
+    /// 1,2
+    /// 3,7
+    /// ```
+    pub fn from_csv<R: Read>(input: R) -> Result<Self, BuddyError> {
+        let mut exclusions = Self::new();
+        for (a, b) in read_id_pairs(input)? {
+            exclusions.insert(a, b);
+        }
+        Ok(exclusions)
+    }
+
+    /// Makes sure `a` and `b` are never grouped together.
+    pub fn insert(&mut self, a: usize, b: usize) {
+        self.pairs.insert((a.min(b), a.max(b)));
+    }
+
+    /// Whether `a` and `b` must stay apart, in either order.
+    pub fn contains(&self, a: usize, b: usize) -> bool {
+        self.pairs.contains(&(a.min(b), a.max(b)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn good() {
+        let e = Exclusions::from_csv("1,2\n3,4".as_bytes()).unwrap();
+        assert_eq!(e.len(), 2);
+        assert!(e.contains(1, 2));
+        assert!(e.contains(2, 1));
+        assert!(!e.contains(1, 3));
+    }
+
+    #[test]
+    fn duplicates_in_either_order() {
+        let e = Exclusions::from_csv("1,2\n2,1".as_bytes()).unwrap();
+        assert_eq!(e.len(), 1);
+    }
+
+    #[test]
+    fn format_wrong() {
+        let r = Exclusions::from_csv("1\n2".as_bytes());
+        assert!(matches!(r, Err(BuddyError::IdPairFormatError)));
+        let r = Exclusions::from_csv("1,Foo".as_bytes());
+        assert!(matches!(r, Err(BuddyError::IdNotANumber)));
+    }
+}
@@ -1,10 +1,18 @@
+mod exclusions;
+mod pins;
+mod preferences;
+
 use crate::BuddyError;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Display;
 use std::io::BufReader;
 use std::io::Read;
 use tracing::info;
 
+pub use exclusions::Exclusions;
+pub use pins::Pins;
+pub use preferences::Preferences;
+
 /// Abstraction over all the people you may want to pair up.
 /// Give it a impl [`Read`], like a file, to get [`People`] back.
 ///
@@ -17,7 +25,10 @@ use tracing::info;
 /// ```
 #[derive(Clone, Debug, Default)]
 pub struct People {
-    people: HashMap<usize, String>,
+    // ordered by id, so iterating the roster is stable between runs
+    people: BTreeMap<usize, String>,
+    /// Extra facts about people, like their team or office, by id.
+    attributes: BTreeMap<usize, BTreeMap<String, String>>,
     evenizer: bool,
 }
 
@@ -41,6 +52,13 @@ impl People {
     /// 2,David
     /// ```
     ///
+    /// Any columns after the name are attributes, written as `key=value`. Not everyone needs
+    /// the same attributes:
+    /// ```text
+    /// 1,John,team=Sales,office=Berlin
+    /// 2,David,team=Engineering
+    /// ```
+    ///
     /// If the given input doesn't contain an even number of people, we will add our own with id
     /// `usize::MAX`, so that id is reserved.
     /// Having that extra user to make it even will keep the algorithm working, so that someone
@@ -51,8 +69,10 @@ impl People {
         let reader = BufReader::new(input);
         let mut rdr = csv::ReaderBuilder::new()
             .has_headers(false)
+            .flexible(true)
             .from_reader(reader);
-        let mut people = HashMap::new();
+        let mut people = BTreeMap::new();
+        let mut attributes = BTreeMap::new();
         let mut tr_input_len = 0;
         for rec in rdr.records() {
             tr_input_len += 1;
@@ -61,6 +81,17 @@ impl People {
                 .map_err(|_| BuddyError::IdNotANumber)?;
             let name = r.get(1).ok_or(BuddyError::CsvFormatError)?.to_string();
 
+            let mut attrs = BTreeMap::new();
+            for field in r.iter().skip(2).filter(|f| !f.trim().is_empty()) {
+                let (key, value) = field
+                    .split_once('=')
+                    .ok_or_else(|| BuddyError::AttributeFormatError(field.to_string()))?;
+                attrs.insert(key.trim().to_string(), value.trim().to_string());
+            }
+            if !attrs.is_empty() {
+                attributes.insert(id, attrs);
+            }
+
             people.insert(id, name);
         }
 
@@ -68,25 +99,65 @@ impl People {
         if people.len() != tr_input_len {
             return Err(BuddyError::IdsNotUnique);
         }
-        let ret = if people.len() % 2 != 0 {
+        let ret = Self::evened(people, attributes);
+
+        info!("Found {} records in input file.", ret.len());
+
+        Ok(ret)
+    }
+
+    /// Adds the evenizer if there's an odd number of people.
+    fn evened(
+        mut people: BTreeMap<usize, String>,
+        attributes: BTreeMap<usize, BTreeMap<String, String>>,
+    ) -> Self {
+        if !people.len().is_multiple_of(2) {
             people.insert(usize::MAX, "EVENIZER".to_string());
             tracing::warn!(
                 "Input people are not even in number, so we can't pair everyone. One person will be left unpaired."
             );
             People {
                 people,
+                attributes,
                 evenizer: true,
             }
         } else {
             People {
                 people,
+                attributes,
                 evenizer: false,
             }
-        };
+        }
+    }
 
-        info!("Found {} records in input file.", ret.len());
+    /// The same people, minus those who are absent this round. The evenizer is added or dropped
+    /// to match how many are left.
+    pub fn without(&self, absent: &BTreeSet<usize>) -> Result<Self, BuddyError> {
+        if let Some(id) = absent.iter().find(|id| !self.people.contains_key(id)) {
+            return Err(BuddyError::UnknownAbsentPerson(*id));
+        }
+        let keep = |id: &usize| *id != usize::MAX && !absent.contains(id);
+        let people = self
+            .people
+            .iter()
+            .filter(|(id, _)| keep(id))
+            .map(|(id, name)| (*id, name.clone()))
+            .collect();
+        let attributes = self
+            .attributes
+            .iter()
+            .filter(|(id, _)| keep(id))
+            .map(|(id, attrs)| (*id, attrs.clone()))
+            .collect();
+        Ok(Self::evened(people, attributes))
+    }
 
-        Ok(ret)
+    /// Everyone's ID and name, ordered by ID. Leaves out the evenizer.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.people
+            .iter()
+            .filter(|(id, _)| **id != usize::MAX)
+            .map(|(id, name)| (*id, name.as_str()))
     }
 
     pub fn len(&self) -> usize {
@@ -106,15 +177,44 @@ impl People {
         self.len() == 0
     }
 
+    /// The value of the given attribute for a person, like their `team`, if they have one.
+    pub fn attribute(&self, id: usize, key: &str) -> Option<&str> {
+        Some(self.attributes.get(&id)?.get(key)?.as_str())
+    }
+
     pub(crate) fn as_ids(&self) -> Vec<usize> {
         self.people.keys().copied().collect()
     }
 
+    pub(crate) fn contains(&self, id: usize) -> bool {
+        self.people.contains_key(&id)
+    }
+
     pub(crate) fn name_from_id(&self, id: usize) -> Option<String> {
         Some(self.people.get(&id)?.to_string())
     }
 }
 
+/// Reads rows of `id,id` from a CSV, like for [`Exclusions`] and [`Pins`].
+fn read_id_pairs<R: Read>(input: R) -> Result<Vec<(usize, usize)>, BuddyError> {
+    let reader = BufReader::new(input);
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(reader);
+    let mut pairs = Vec::new();
+    for rec in rdr.records() {
+        let r = rec?;
+        let mut ids = Vec::new();
+        for i in 0..2 {
+            let id = r.get(i).ok_or(BuddyError::IdPairFormatError)?.trim();
+            ids.push(str::parse::<usize>(id).map_err(|_| BuddyError::IdNotANumber)?);
+        }
+        pairs.push((ids[0], ids[1]));
+    }
+    Ok(pairs)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -149,6 +249,44 @@ mod test {
         assert!(matches!(r, Err(BuddyError::IdsNotUnique)));
     }
     #[test]
+    fn attributes() {
+        let csv = "1,Foo,team=Sales, office = Berlin\n2,Bar\n3,Baz,team=Ops,".as_bytes();
+        let r = People::from_csv(csv).unwrap();
+        assert_eq!(r.len(), 3);
+        assert_eq!(r.attribute(1, "team"), Some("Sales"));
+        assert_eq!(r.attribute(1, "office"), Some("Berlin"));
+        assert_eq!(r.attribute(2, "team"), None);
+        assert_eq!(r.attribute(3, "team"), Some("Ops"));
+    }
+    #[test]
+    fn attribute_format_wrong() {
+        let csv = "1,Foo,Sales".as_bytes();
+        let r = People::from_csv(csv);
+        assert!(matches!(r, Err(BuddyError::AttributeFormatError(_))));
+    }
+    #[test]
+    fn without_absent() {
+        let csv = "1,Foo,team=A\n2,Bar\n3,Baz\n4,Qux".as_bytes();
+        let r = People::from_csv(csv).unwrap();
+        let present = r.without(&BTreeSet::from([2])).unwrap();
+        assert_eq!(present.len(), 3);
+        assert!(present.has_evenizer());
+        assert!(!present.contains(2));
+        assert_eq!(present.attribute(1, "team"), Some("A"));
+
+        // and back to even again
+        let present = present.without(&BTreeSet::from([3])).unwrap();
+        assert_eq!(present.len(), 2);
+        assert!(!present.has_evenizer());
+        assert_eq!(
+            present.iter().collect::<Vec<_>>(),
+            vec![(1, "Foo"), (4, "Qux")]
+        );
+
+        let r = r.without(&BTreeSet::from([9]));
+        assert!(matches!(r, Err(BuddyError::UnknownAbsentPerson(9))));
+    }
+    #[test]
     fn csv_format_wrong() {
         let csv = "1\n2".as_bytes();
         let r = People::from_csv(csv);
@@ -0,0 +1,71 @@
+use super::read_id_pairs;
+use crate::BuddyError;
+use std::io::Read;
+
+/// Pairs of people that must be paired with each other this round, like a new hire and their
+/// onboarding buddy. Give it a impl [`Read`], like a file, to get [`Pins`] back.
+///
+/// Example:
+/// ```ignore
+/// # use std::fs::File;
+/// # use buddy_up_lib::Pins;
+/// let f = File::open("pins.csv")?;
+/// let pins = Pins::from_csv(f)?;
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Pins {
+    pairs: Vec<(usize, usize)>,
+}
+
+impl Pins {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads pins from a CSV file. The expected format is rows of two IDs, `id,id`.
+    ///
+    /// Example CSV:
+    /// ```text
+    /// 1,2
+    /// 3,7
+    /// ```
+    pub fn from_csv<R: Read>(input: R) -> Result<Self, BuddyError> {
+        Ok(Self {
+            pairs: read_id_pairs(input)?,
+        })
+    }
+
+    /// Pins `a` and `b` together.
+    pub fn insert(&mut self, a: usize, b: usize) {
+        self.pairs.push((a, b));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(usize, usize)> {
+        self.pairs.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn good() {
+        let p = Pins::from_csv("1,2\n3, 4".as_bytes()).unwrap();
+        assert_eq!(p.iter().copied().collect::<Vec<_>>(), vec![(1, 2), (3, 4)]);
+    }
+
+    #[test]
+    fn format_wrong() {
+        let r = Pins::from_csv("1".as_bytes());
+        assert!(matches!(r, Err(BuddyError::IdPairFormatError)));
+    }
+}
@@ -0,0 +1,120 @@
+use crate::BuddyError;
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::io::Read;
+
+/// Who people would like to meet, or would rather not. Every rating goes from -1.0, rather not,
+/// to 1.0, would love to, and is one person's view of another: `a` may be keen to meet `b`
+/// without `b` feeling the same. Give it a impl [`Read`], like a file, to get [`Preferences`]
+/// back.
+///
+/// Example:
+/// ```ignore
+/// # use std::fs::File;
+/// # use buddy_up_lib::Preferences;
+/// let f = File::open("preferences.csv")?;
+/// let preferences = Preferences::from_csv(f)?;
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Preferences {
+    ratings: HashMap<(usize, usize), f64>,
+}
+
+impl Preferences {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads preferences from a CSV file. The expected format is rows of `id,id,rating`, where
+    /// the first person rates the second.
+    ///
+    /// Example CSV:
+    /// ```text
+    /// 1,2,1.0
+    /// 2,1,0.5
+    /// 3,7,-1
+    /// ```
+    pub fn from_csv<R: Read>(input: R) -> Result<Self, BuddyError> {
+        let reader = BufReader::new(input);
+        let mut rdr = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(reader);
+        let mut preferences = Self::new();
+        for rec in rdr.records() {
+            let r = rec?;
+            let field = |i| {
+                r.get(i)
+                    .map(str::trim)
+                    .ok_or(BuddyError::PreferenceFormatError)
+            };
+            let from = str::parse::<usize>(field(0)?).map_err(|_| BuddyError::IdNotANumber)?;
+            let to = str::parse::<usize>(field(1)?).map_err(|_| BuddyError::IdNotANumber)?;
+            let rating = str::parse::<f64>(field(2)?)
+                .ok()
+                .filter(|rating| (-1.0..=1.0).contains(rating))
+                .ok_or(BuddyError::PreferenceFormatError)?;
+            preferences.insert(from, to, rating);
+        }
+        Ok(preferences)
+    }
+
+    /// Records how much `from` would like to meet `to`, clamped to -1.0 to 1.0.
+    pub fn insert(&mut self, from: usize, to: usize, rating: f64) {
+        self.ratings.insert((from, to), rating.clamp(-1.0, 1.0));
+    }
+
+    /// How much `from` would like to meet `to`, or 0.0 if they didn't say.
+    pub fn get(&self, from: usize, to: usize) -> f64 {
+        self.ratings.get(&(from, to)).copied().unwrap_or(0.0)
+    }
+
+    /// How much `a` and `b` would like to meet each other, the average of both their ratings.
+    pub fn mutual(&self, a: usize, b: usize) -> f64 {
+        f64::midpoint(self.get(a, b), self.get(b, a))
+    }
+
+    /// The best [`mutual`](Self::mutual) rating of any pair, or 0.0 if nobody is keen.
+    pub(crate) fn best(&self) -> f64 {
+        self.ratings
+            .keys()
+            .map(|&(a, b)| self.mutual(a, b))
+            .fold(0.0, f64::max)
+    }
+
+    pub fn len(&self) -> usize {
+        self.ratings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn good() {
+        let p = Preferences::from_csv("1,2,1\n2,1,0.5\n3, 4, -1".as_bytes()).unwrap();
+        assert_eq!(p.len(), 3);
+        assert_eq!(p.get(1, 2), 1.0);
+        assert_eq!(p.mutual(2, 1), 0.75);
+        assert_eq!(p.mutual(3, 4), -0.5);
+        assert_eq!(p.mutual(1, 3), 0.0);
+        assert_eq!(p.best(), 0.75);
+    }
+
+    #[test]
+    fn format_wrong() {
+        let r = Preferences::from_csv("1,2".as_bytes());
+        assert!(matches!(r, Err(BuddyError::PreferenceFormatError)));
+        let r = Preferences::from_csv("1,2,lots".as_bytes());
+        assert!(matches!(r, Err(BuddyError::PreferenceFormatError)));
+        let r = Preferences::from_csv("1,2,5".as_bytes());
+        assert!(matches!(r, Err(BuddyError::PreferenceFormatError)));
+        let r = Preferences::from_csv("1,Foo,1".as_bytes());
+        assert!(matches!(r, Err(BuddyError::IdNotANumber)));
+    }
+}
@@ -10,7 +10,10 @@ use thiserror::Error;
 
 pub use algorithm::history::*;
 pub use algorithm::*;
+pub use input::Exclusions;
 pub use input::People;
+pub use input::Pins;
+pub use input::Preferences;
 pub use output::*;
 
 use serde::Deserialize;
@@ -37,6 +40,9 @@ pub enum BuddyError {
     #[error("Given ID is not a number. Check input.")]
     IdNotANumber,
 
+    #[error("Attribute '{0}' should look like 'key=value', like 'team=Sales'.")]
+    AttributeFormatError(String),
+
     #[error("The given IDs are not unique. Check input.")]
     IdsNotUnique,
 
@@ -45,6 +51,79 @@ pub enum BuddyError {
 
     #[error("Error reading history, make sure there's nothing wrong with the directory name.")]
     PatternError(#[from] PatternError),
+
+    #[error("Group size {0} is too small, groups need at least 2 people.")]
+    InvalidGroupSize(usize),
+
+    #[error("The exact solver only works for pairs, not groups of {0}.")]
+    ExactSolverNeedsPairs(usize),
+
+    #[error("Pairs of IDs should be rows of 'id,id', like '1,2'.")]
+    IdPairFormatError,
+
+    #[error(
+        "Preferences should be rows of 'id,id,rating' with a rating from -1 to 1, like '1,2,0.5'."
+    )]
+    PreferenceFormatError,
+
+    #[error(
+        "Couldn't find groups that keep apart everyone who must never meet. Check the exclusions, working hours and cooldown."
+    )]
+    UnsatisfiableConstraints,
+
+    #[error("Person with ID {0} is pinned, but isn't in the input.")]
+    UnknownPinnedPerson(usize),
+
+    #[error("Person with ID {0} is pinned more than once.")]
+    PinnedTwice(usize),
+
+    #[error("Person with ID {0} is absent, but isn't in the input.")]
+    UnknownAbsentPerson(usize),
+
+    #[error("Person with ID {0} is pinned, but absent this round.")]
+    PinnedAbsent(usize),
+
+    #[error("Person with ID {0} is in the round to re-roll, but isn't in the input.")]
+    UnknownRerollPerson(usize),
+
+    #[error(
+        "IDs {0} and {1} aren't in the same group of the round to re-roll, so they can't be kept."
+    )]
+    NotInRound(usize, usize),
+
+    #[error("There's no group {0} to keep, the round to re-roll only has {1}.")]
+    NoSuchGroup(usize, usize),
+
+    #[error("Invalid solver setting: {0}.")]
+    InvalidSolverParameter(String),
+
+    #[error("Pairing was cancelled.")]
+    Cancelled,
+
+    #[error(
+        "Ran out of time before finding groups that keep all the rules. There may be none, or it needs a longer time limit."
+    )]
+    OutOfTime,
+
+    #[error("There's nobody to pair. Add some people to the input.")]
+    EmptyRoster,
+
+    #[error("Pairing needs at least two people, but there's only {0}.")]
+    TooFewPeople(usize),
+
+    #[error("The solver failed: {0}.")]
+    SolverFailure(String),
+
+    #[error(
+        "Person with ID {0} has a time zone or working hours we can't read. Use something like 'timezone=+5:30' and 'hours=9-17'."
+    )]
+    TimeZoneFormatError(usize),
+
+    #[error("Mentoring only works for pairs, not groups of {0}.")]
+    MentoringNeedsPairs(usize),
+
+    #[error("Person with ID {0} needs a 'role=mentor' or 'role=mentee' attribute for mentoring.")]
+    InvalidRole(usize),
 }
 
 #[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
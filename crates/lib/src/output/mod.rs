@@ -1,6 +1,7 @@
 use crate::BuddyError;
-use crate::Pairs;
+use crate::Groups;
 use crate::Person;
+use crate::history::FILE_NAME_FORMAT;
 use chrono::Local;
 use comfy_table::Table;
 use std::fs::File;
@@ -8,12 +9,12 @@ use std::io::Write;
 use std::path::PathBuf;
 use tracing::info;
 
-/// Write the JSON history of this pairing to the given directory
-pub fn save_history(pairs: &Pairs, dir: &str) -> Result<(), BuddyError> {
+/// Write the JSON history of this pairing to the given directory, and return the new file's path
+pub fn save_history(groups: &Groups, dir: &str) -> Result<PathBuf, BuddyError> {
     // serialize to json and save
-    let json = serde_json::to_string_pretty(&pairs)?;
+    let json = serde_json::to_string_pretty(&groups)?;
     let date_time = Local::now();
-    let formatted = format!("{}", date_time.format("%Y%m%d_%H%M%S"));
+    let formatted = format!("{}", date_time.format(FILE_NAME_FORMAT));
     let filename = format!("{formatted}.json");
 
     let mut path = PathBuf::new();
@@ -26,32 +27,31 @@ pub fn save_history(pairs: &Pairs, dir: &str) -> Result<(), BuddyError> {
     let mut file = File::create(&path)?;
     file.write_all(json.as_bytes())?;
     info!("Saved history file to {path:?}");
-    Ok(())
+    Ok(path)
 }
 
-/// Prints a pretty table of pairs.
+/// Prints a pretty table of groups, one row per group.
 ///
-/// If one pair includes the Evenizer with id usize::MAX, don't include the real user paired with
-/// the Evenizer, and instead print a note below the table with that user not being paired.
-pub fn print_table(pairs: Pairs) -> String {
-    // now print the pairs
+/// If a group includes the Evenizer with id usize::MAX, leave the Evenizer out. If that leaves a
+/// real user on their own, print a note below the table with that user not being paired instead.
+/// The same goes for anyone sitting out in a group of their own.
+pub fn print_table(groups: Groups) -> String {
+    // now print the groups
     let mut table = Table::new();
 
-    let mut unpaired: Option<Person> = None;
+    let mut unpaired: Vec<String> = Vec::new();
 
-    // if a pair includes our Evenizer with id usize::MAX, don't pair that one and just print as not paired.
-    for pair in pairs.inner() {
-        if pair.1.id == usize::MAX {
-            unpaired = Some(pair.0);
-        } else if pair.0.id == usize::MAX {
-            unpaired = Some(pair.1);
-        } else {
-            table.add_row(vec![pair.0.clone(), pair.1.clone()]);
+    for group in groups.inner() {
+        let mut group: Vec<Person> = group.into_iter().filter(|p| p.id != usize::MAX).collect();
+        if group.len() == 1 {
+            unpaired.extend(group.pop().map(|p| p.to_string()));
+        } else if !group.is_empty() {
+            table.add_row(group);
         }
     }
     let mut table = table.to_string();
-    if let Some(unpaired) = unpaired {
-        table.push_str(&format!("\nNot paired: {unpaired}"));
+    if !unpaired.is_empty() {
+        table.push_str(&format!("\nNot paired: {}", unpaired.join(", ")));
     }
     table
 }